@@ -0,0 +1,15 @@
+use xcb::atoms_struct;
+
+atoms_struct! {
+    /// Atoms which are not predefined by the core protocol, interned once
+    /// at startup
+    pub struct Atoms {
+        pub net_wm_name => b"_NET_WM_NAME" only_if_exists = false,
+        pub utf8_string => b"UTF8_STRING" only_if_exists = false,
+        pub manager => b"MANAGER" only_if_exists = false,
+        pub net_system_tray_opcode => b"_NET_SYSTEM_TRAY_OPCODE" only_if_exists = false,
+        pub net_system_tray_orientation => b"_NET_SYSTEM_TRAY_ORIENTATION" only_if_exists = false,
+        pub xembed => b"_XEMBED" only_if_exists = false,
+        pub xembed_info => b"_XEMBED_INFO" only_if_exists = false,
+    }
+}
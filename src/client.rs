@@ -6,9 +6,45 @@ use crate::error::Error;
 
 use xcb::x;
 
+/// Border width and colours for clients. Colours are pixel values.
+#[derive(Debug, Clone, Copy)]
+pub struct Borders {
+    pub width: u16,
+    pub focus: u32,
+    pub unfocus: u32,
+    pub urgent: u32,
+    pub floating: u32,
+    /// Hide the border of a client which fills its whole monitor, such as
+    /// one which is alone or in Monacle
+    pub smart: bool,
+}
+
+impl Borders {
+    /// Get the colour for a client in a given state
+    pub fn pixel(&self, client: &Client, focused: bool) -> u32 {
+        if focused {
+            self.focus
+        } else if client.urgent {
+            self.urgent
+        } else if client.floating {
+            self.floating
+        } else {
+            self.unfocus
+        }
+    }
+}
+
 pub struct Client {
     window: x::Window,
+    name: String,
     visible: bool,
+    urgent: bool,
+    floating: bool,
+    /* the border width we want, and the width and colour last sent to
+     * the server */
+    border: u16,
+    shown: u16,
+    pixel: Option<u32>,
     rect: Rect,
     mask: SlabMap<TagMask>,
 }
@@ -19,22 +55,100 @@ impl Client {
         self.window
     }
 
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    #[inline]
+    pub fn set_name(&mut self, name: String) {
+        self.name = name;
+    }
+
     #[inline]
     pub fn visible(&self) -> bool {
         self.visible
     }
 
+    #[inline]
+    pub fn urgent(&self) -> bool {
+        self.urgent
+    }
+
+    #[inline]
+    pub fn set_urgent(&mut self, urgent: bool) {
+        self.urgent = urgent;
+    }
+
+    #[inline]
+    pub fn floating(&self) -> bool {
+        self.floating
+    }
+
+    #[inline]
+    pub fn set_floating(&mut self, floating: bool) {
+        self.floating = floating;
+    }
+
+    /// Get the border width of the client, which may be hidden when it is
+    /// arranged
+    #[inline]
+    pub fn border(&self) -> u16 {
+        self.border
+    }
+
+    #[inline]
+    pub fn set_border(&mut self, border: u16) {
+        self.border = border;
+    }
+
+    /// Get the rect the client was last given, including its border
     #[inline]
     pub fn rect(&self) -> &Rect {
         &self.rect
     }
+
+    /// Get the geometry of the window inside its border, along with the
+    /// border width, as last sent to the server
+    pub fn geometry(&self) -> (Rect, u16) {
+        let inner = Rect::new(
+            self.rect.x,
+            self.rect.y,
+            self.rect.w.saturating_sub(2 * self.shown),
+            self.rect.h.saturating_sub(2 * self.shown));
+
+        (inner, self.shown)
+    }
 }
 
 impl Client {
     pub fn new(window: x::Window, rect: Rect) -> Self {
         Client {
             window: window,
+            name: String::new(),
             visible: false,
+            urgent: false,
+            floating: false,
+            border: 0,
+            shown: 0,
+            pixel: None,
+            rect: rect,
+            mask: SlabMap::new(),
+        }
+    }
+
+    /// Create a client for a window that is already mapped, such as one
+    /// adopted from a previous window manager
+    pub fn mapped(window: x::Window, rect: Rect) -> Self {
+        Client {
+            window: window,
+            name: String::new(),
+            visible: true,
+            urgent: false,
+            floating: false,
+            border: 0,
+            shown: 0,
+            pixel: None,
             rect: rect,
             mask: SlabMap::new(),
         }
@@ -72,17 +186,42 @@ impl Client {
         Ok(())
     }
 
-    pub fn resize<T>(&mut self, conn: &mut Connection<T>, rect: &Rect) -> Result<(), Error> {
-        if &self.rect != rect {
+    /// Move the client to fill a rect, with a border of the given width
+    /// drawn inside it
+    pub fn resize<T>(&mut self, conn: &mut Connection<T>, rect: &Rect, border: u16) -> Result<(), Error> {
+        if &self.rect != rect || self.shown != border {
             self.rect = *rect;
+            self.shown = border;
+
+            let (inner, _) = self.geometry();
 
             let cookie = conn.send_request_checked(&x::ConfigureWindow {
                 window: self.window,
                 value_list: &[
-                    x::ConfigWindow::X(self.rect.x as i32),
-                    x::ConfigWindow::Y(self.rect.y as i32),
-                    x::ConfigWindow::Width(self.rect.w as u32),
-                    x::ConfigWindow::Height(self.rect.h as u32),
+                    x::ConfigWindow::X(inner.x as i32),
+                    x::ConfigWindow::Y(inner.y as i32),
+                    /* zero sized windows are an error */
+                    x::ConfigWindow::Width(inner.w.max(1) as u32),
+                    x::ConfigWindow::Height(inner.h.max(1) as u32),
+                    x::ConfigWindow::BorderWidth(border as u32),
+                ],
+            });
+
+            conn.check_request(cookie)?;
+        }
+
+        Ok(())
+    }
+
+    /// Set the border colour of the client
+    pub fn paint<T>(&mut self, conn: &mut Connection<T>, pixel: u32) -> Result<(), Error> {
+        if self.pixel != Some(pixel) {
+            self.pixel = Some(pixel);
+
+            let cookie = conn.send_request_checked(&x::ChangeWindowAttributes {
+                window: self.window,
+                value_list: &[
+                    x::Cw::BorderPixel(pixel),
                 ],
             });
 
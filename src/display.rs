@@ -2,12 +2,12 @@ use std::collections::HashMap;
 use std::ops::{Index, IndexMut};
 
 use crate::slab::AsIndex;
-use crate::client::Client;
+use crate::client::{Borders, Client};
 use crate::error::Error;
-use crate::layout::{Layout, LeftMaster};
-use crate::rect::Rect;
-use crate::tag::TagSelection;
-use crate::window::{WindowTree, ClientId, LayoutId, Window, Clients};
+use crate::layout::{Cycle, Decoration, Gaps, Layout, LayoutMessage, LeftMaster};
+use crate::rect::{Direction, Rect};
+use crate::tag::{TagSelection, TagSetId, View};
+use crate::window::{WindowTree, ClientId, LayoutId, Window, Clients, Walk};
 use crate::wm::{Connection, Event};
 
 use xcb::{randr, x};
@@ -24,6 +24,15 @@ impl AsIndex for MonitorId {
     }
 }
 
+impl From<usize> for MonitorId {
+    #[inline]
+    fn from(i: usize) -> Self {
+        MonitorId {
+            inner: i
+        }
+    }
+}
+
 /// Get a vector of all monitors with active outputs
 fn monitors<T>(
     conn: &Connection<T>,
@@ -104,19 +113,40 @@ fn monitors<T>(
     Ok((monitors, primary))
 }
 
+/// How new clients are added to a monitor's window tree
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Insert {
+    /// Add clients to the layout holding the focused client
+    Append,
+    /// Split the focused client in two, nesting both in a new container
+    Split,
+}
+
 /// A Monitor is a combination of a WindowTree and an actual monitor output.
 /// It mostly mirrors the WindowTree API, but adds some metadata relating
 /// to the actual monitor such as size and name.
 pub struct Monitor {
     window: x::Window,
+    bar: Option<x::Window>,
     name: String,
     primary: bool,
     pub focus: Option<ClientId>,
     rect: Rect,
+    borders: Borders,
+    gaps: Gaps,
+    insert: Insert,
+    /* where the next split puts the new client */
+    preselect: Option<Direction>,
+    /* the tags last arranged, and root layouts saved for other views */
+    view: Option<View>,
+    layouts: Vec<(View, Box<dyn Layout>)>,
     tree: WindowTree,
 }
 
 impl Monitor {
+    /// The most root layouts kept for views other than the current one
+    const VIEWS: usize = 16;
+
     fn new<T>(
         conn: &Connection<T>,
         root: x::Window,
@@ -125,19 +155,47 @@ impl Monitor {
         let cookie = conn.send_request(&x::GetAtomName { atom: info.name() });
 
         let rect = Rect::new(info.x(), info.y(), info.width(), info.height());
-        let tree = WindowTree::new(LeftMaster::new());
 
         let reply = conn.wait_for_reply(cookie)?;
         let name = String::from(reply.name().to_utf8());
 
-        Ok(Monitor {
+        Ok(Monitor::create(root, name, info.primary(), rect))
+    }
+
+    /// Create a monitor without asking the server about it
+    fn create(root: x::Window, name: String, primary: bool, rect: Rect) -> Self {
+        let tree = WindowTree::new(LeftMaster::new());
+
+        /* replaced by the display's borders when the monitor is added */
+        let borders = Borders {
+            width: 0,
+            focus: 0,
+            unfocus: 0,
+            urgent: 0,
+            floating: 0,
+            smart: false,
+        };
+
+        Monitor {
             window: root,
+            bar: None,
             name: name,
-            primary: info.primary(),
+            primary: primary,
             focus: None,
             rect: rect,
+            borders: borders,
+            gaps: Gaps::default(),
+            insert: Insert::Append,
+            preselect: None,
+            view: None,
+            layouts: vec![],
             tree: tree,
-        })
+        }
+    }
+
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.name
     }
 
     pub fn get_rect(&mut self) -> &Rect {
@@ -147,6 +205,100 @@ impl Monitor {
     pub fn set_rect(&mut self, rect: Rect) {
         self.rect = rect;
     }
+
+    /// Get the symbol of the root layout
+    #[inline]
+    pub fn symbol(&self) -> &str {
+        self.tree[self.tree.root()].symbol()
+    }
+
+    /// Get the title of the focused client
+    #[inline]
+    pub fn title(&self) -> Option<&str> {
+        self.focus.map(|id| self.tree[id].name())
+    }
+
+    #[inline]
+    pub fn gaps(&self) -> &Gaps {
+        &self.gaps
+    }
+
+    /// Change the gaps of this monitor. This takes effect the next time it
+    /// is arranged.
+    #[inline]
+    pub fn set_gaps(&mut self, gaps: Gaps) {
+        self.gaps = gaps;
+    }
+
+    /// Get the bar window for this monitor, if one has been created
+    #[inline]
+    pub fn bar(&self) -> Option<x::Window> {
+        self.bar
+    }
+
+    /// Create the bar window for this monitor, or move it if it already
+    /// exists. The bar is override-redirect so it is never managed as a
+    /// client, and it receives Expose and ButtonPress events.
+    pub fn set_bar<T>(&mut self, conn: &mut Connection<T>, rect: &Rect) -> Result<x::Window, Error> {
+        if let Some(bar) = self.bar {
+            conn.send_and_check_request(&x::ConfigureWindow {
+                window: bar,
+                value_list: &[
+                    x::ConfigWindow::X(rect.x as i32),
+                    x::ConfigWindow::Y(rect.y as i32),
+                    x::ConfigWindow::Width(rect.w as u32),
+                    x::ConfigWindow::Height(rect.h as u32),
+                ],
+            })?;
+
+            return Ok(bar);
+        }
+
+        let screen = conn.get_setup()
+            .roots()
+            .nth(conn.screen())
+            .ok_or(Error::MissingScreen)?;
+
+        let bar: x::Window = conn.generate_id();
+        let visual = screen.root_visual();
+        let background = screen.black_pixel();
+
+        conn.send_and_check_request(&x::CreateWindow {
+            depth: x::COPY_FROM_PARENT as u8,
+            wid: bar,
+            parent: self.window,
+            x: rect.x,
+            y: rect.y,
+            width: rect.w,
+            height: rect.h,
+            border_width: 0,
+            class: x::WindowClass::InputOutput,
+            visual: visual,
+            value_list: &[
+                x::Cw::BackPixel(background),
+                x::Cw::OverrideRedirect(true),
+                x::Cw::EventMask(x::EventMask::EXPOSURE | x::EventMask::BUTTON_PRESS),
+            ],
+        })?;
+
+        conn.send_and_check_request(&x::MapWindow {
+            window: bar,
+        })?;
+
+        self.bar = Some(bar);
+
+        Ok(bar)
+    }
+
+    fn destroy_bar<T>(&mut self, conn: &mut Connection<T>) -> Result<(), Error> {
+        if let Some(bar) = self.bar.take() {
+            conn.send_and_check_request(&x::DestroyWindow {
+                window: bar,
+            })?;
+        }
+
+        Ok(())
+    }
 }
 
 impl Monitor {
@@ -158,11 +310,57 @@ impl Monitor {
         conn: &mut Connection<T>,
         mask: &TagSelection<'a, 'b>,
     ) -> Result<(), Error> {
-        self.focus = self.tree.arrange(conn, mask, &self.rect)?;
+        let view = mask.view();
+
+        if self.view.as_ref() != Some(&view) {
+            self.restore(view);
+        }
+
+        self.focus = self.tree.arrange(conn, mask, &self.rect, &self.borders, &self.gaps, self.focus)?;
         Ok(())
     }
 
-    fn focused_layout(&self) -> LayoutId {
+    /// Switch the root layout to the one last used with a view, saving
+    /// the current one for the view being left. Views not seen before
+    /// start with the default layout, and the least recently left views
+    /// are forgotten once too many are saved.
+    fn restore(&mut self, view: View) {
+        if let Some(previous) = self.view.take() {
+            let layout = match self.layouts.iter().position(|(v, _)| *v == view) {
+                Some(i) => self.layouts.remove(i).1,
+                None => Box::new(LeftMaster::new()),
+            };
+
+            let root = self.tree.root();
+            let layout = self.tree.set_layout(root, layout);
+
+            self.layouts.push((previous, layout));
+
+            if self.layouts.len() > Monitor::VIEWS {
+                let excess = self.layouts.len() - Monitor::VIEWS;
+                self.layouts.drain(..excess);
+            }
+        }
+
+        self.view = Some(view);
+    }
+
+    /// Drop the layouts saved for views of a tagset which no longer exists
+    pub fn forget(&mut self, id: TagSetId) {
+        self.layouts.retain(|(view, _)| !view.contains(id));
+    }
+
+    /// Repaint the border of a client, after its state has changed
+    pub fn paint<T>(&mut self, conn: &mut Connection<T>, id: ClientId) -> Result<(), Error> {
+        let focused = self.focus == Some(id);
+        let pixel = self.borders.pixel(&self.tree[id], focused);
+
+        self.tree[id].paint(conn, pixel)
+    }
+
+    /// Get the layout containing the focused client, or the root layout if
+    /// nothing is focused
+    pub fn focused_layout(&self) -> LayoutId {
         match self.focus {
             /* clients are guarenteed to have a parent */
             Some(client) => self.tree.parent(client).unwrap(),
@@ -170,9 +368,59 @@ impl Monitor {
         }
     }
 
+    /// Send a message to the focused layout, returning true if it needs to
+    /// be rearranged
+    pub fn message(&mut self, msg: LayoutMessage) -> bool {
+        let id = self.focused_layout();
+        self.tree[id].handle(msg)
+    }
+
+    /// Add a client with the monitor's border width. It either goes into
+    /// the focused layout, or splits the focused client in split mode.
+    pub fn client(&mut self, mut client: Client) -> ClientId {
+        client.set_border(self.borders.width);
+
+        match (self.insert, self.focus) {
+            (Insert::Split, Some(focus)) => {
+                /* without a preselection, split along the longer side */
+                let direction = self.preselect.take().unwrap_or_else(|| {
+                    let rect = self.tree[focus].rect();
+
+                    if rect.w >= rect.h {
+                        Direction::Right
+                    } else {
+                        Direction::Down
+                    }
+                });
+
+                self.tree.split(focus, client, direction)
+            }
+            _ => {
+                self.tree.client(self.focused_layout(), client)
+            }
+        }
+    }
+
     #[inline]
-    pub fn client(&mut self, client: Client) -> ClientId {
-        self.tree.client(self.focused_layout(), client)
+    pub fn insert(&self) -> Insert {
+        self.insert
+    }
+
+    #[inline]
+    pub fn set_insert(&mut self, insert: Insert) {
+        self.insert = insert;
+    }
+
+    #[inline]
+    pub fn preselect(&self) -> Option<Direction> {
+        self.preselect
+    }
+
+    /// Choose where the next split puts the new client. The preselection
+    /// is used up by the split.
+    #[inline]
+    pub fn set_preselect(&mut self, direction: Option<Direction>) {
+        self.preselect = direction;
     }
 
     #[inline]
@@ -180,35 +428,119 @@ impl Monitor {
         self.tree.clients()
     }
 
+    #[inline]
+    pub fn walk<'a>(&'a self) -> Walk<'a> {
+        self.tree.walk()
+    }
+
+    /// Get the windows drawn for layout decorations on this monitor
+    #[inline]
+    pub fn decorations(&self) -> impl Iterator<Item = (x::Window, &Rect, &Decoration)> {
+        self.tree.decorations()
+    }
+
+    #[inline]
+    pub fn decorated(&self, window: x::Window) -> bool {
+        self.tree.decorated(window)
+    }
+
+    /// Update the title of a client in any decoration showing it,
+    /// returning true if the decorations need to be drawn again
+    #[inline]
+    pub fn retitle(&mut self, id: ClientId) -> bool {
+        self.tree.retitle(id)
+    }
+
     #[inline]
     pub fn layout(&mut self, layout: impl Layout + 'static) -> LayoutId {
         self.tree.layout(self.focused_layout(), layout)
     }
 
+    /// Replace the layout of a container, keeping its children
+    #[inline]
+    pub fn set_layout(&mut self, id: LayoutId, layout: Box<dyn Layout>) -> Box<dyn Layout> {
+        self.tree.set_layout(id, layout)
+    }
+
+    /// Switch the focused layout to the next one in a cycle, returning
+    /// false if the cycle is empty
+    pub fn cycle(&mut self, cycle: &Cycle) -> bool {
+        let id = self.focused_layout();
+
+        match cycle.next(&self.tree[id]) {
+            Some(layout) => {
+                self.tree.set_layout(id, layout);
+                true
+            }
+            None => false,
+        }
+    }
+
     #[inline]
     pub fn find(&self, window: x::Window) -> Option<ClientId> {
         self.tree.find(window)
     }
 
-    #[inline]
     pub fn remove<I: AsIndex>(&mut self, id: I) -> Window {
+        /* the index may be reused before the next arrange */
+        if self.focus.map(|focus| focus.as_index()) == Some(id.as_index()) {
+            self.focus = None;
+        }
+
         self.tree.remove(id)
     }
 
+    /// Swap the focused client with the next client shown in its layout
+    pub fn swap_next<'a, 'b>(&mut self, mask: &TagSelection<'a, 'b>) -> bool {
+        match self.focus {
+            Some(id) => self.tree.swap_next(id, mask),
+            None => false,
+        }
+    }
+
+    /// Swap the focused client with the previous client shown in its layout
+    pub fn swap_previous<'a, 'b>(&mut self, mask: &TagSelection<'a, 'b>) -> bool {
+        match self.focus {
+            Some(id) => self.tree.swap_previous(id, mask),
+            None => false,
+        }
+    }
+
+    /// Promote the focused client to the master slot of its layout
+    pub fn zoom<'a, 'b>(&mut self, mask: &TagSelection<'a, 'b>) -> bool {
+        match self.focus {
+            Some(id) => self.tree.zoom(id, mask),
+            None => false,
+        }
+    }
+
+    /// Move the focused client into the nearest container beside it
+    pub fn move_into_sibling(&mut self, forward: bool) -> bool {
+        match self.focus {
+            Some(id) => self.tree.move_into_sibling(id, forward).is_some(),
+            None => false,
+        }
+    }
+
+    /// Rotate the children of the focused layout shown in a tag selection
+    pub fn rotate<'a, 'b>(&mut self, forward: bool, mask: &TagSelection<'a, 'b>) -> bool {
+        let id = self.focused_layout();
+        self.tree.rotate(id, forward, mask)
+    }
+
     #[inline]
     pub fn parent<I: AsIndex>(&self, i: I) -> Option<LayoutId> {
         self.tree.parent(i)
     }
 
     #[inline]
-    pub fn next_client<I: AsIndex>(&self, i: I) -> Option<ClientId> {
-        self.tree.next_client(i)
+    pub fn next_visible<'a, 'b>(&self, id: Option<ClientId>, mask: &TagSelection<'a, 'b>) -> Option<ClientId> {
+        self.tree.next_visible(id, mask)
     }
 
-
     #[inline]
-    pub fn previous_client<I: AsIndex>(&self, i: I) -> Option<ClientId> {
-        self.tree.previous_client(i)
+    pub fn previous_visible<'a, 'b>(&self, id: Option<ClientId>, mask: &TagSelection<'a, 'b>) -> Option<ClientId> {
+        self.tree.previous_visible(id, mask)
     }
 
 
@@ -263,23 +595,48 @@ pub struct Display {
     monitors: slab::Slab<Monitor>,
     primary: Option<MonitorId>,
     focus: Option<MonitorId>,
+    borders: Borders,
+    gaps: Gaps,
 }
 
 impl Display {
     pub fn new<T>(conn: &mut Connection<T>, root: x::Window) -> Result<Self, Error> {
-        let mut display = Display {
-            root: root,
-            monitors: slab::Slab::new(),
-            primary: None,
-            focus: None,
+        let screen = conn.get_setup()
+            .roots()
+            .nth(conn.screen())
+            .ok_or(Error::MissingScreen)?;
+
+        let borders = Borders {
+            width: 2,
+            focus: screen.white_pixel(),
+            unfocus: screen.black_pixel(),
+            urgent: screen.white_pixel(),
+            floating: screen.black_pixel(),
+            smart: false,
         };
 
+        let mut display = Display::create(root, borders);
         display.update(conn)?;
 
         Ok(display)
     }
 
-    fn insert(&mut self, mon: Monitor) -> MonitorId {
+    /// Create a display with no monitors, without asking the server
+    fn create(root: x::Window, borders: Borders) -> Self {
+        Display {
+            root: root,
+            monitors: slab::Slab::new(),
+            primary: None,
+            focus: None,
+            borders: borders,
+            gaps: Gaps::default(),
+        }
+    }
+
+    fn insert(&mut self, mut mon: Monitor) -> MonitorId {
+        mon.borders = self.borders;
+        mon.gaps = self.gaps;
+
         let index = self.monitors.insert(mon);
 
         let id = MonitorId { inner: index };
@@ -311,10 +668,48 @@ impl Display {
         Ok(())
     }
 
+    /// Remove a monitor which is no longer connected. Its clients are handed
+    /// over to the focused monitor, and their new ids sent with the event.
+    fn remove<T>(&mut self, conn: &mut Connection<T>, id: MonitorId) -> Result<(), Error> {
+        let mut mon = self.monitors.remove(id.inner);
+
+        mon.destroy_bar(conn)?;
+
+        if self.primary == Some(id) {
+            self.primary = None;
+        }
+
+        if self.focus == Some(id) {
+            self.focus = self.primary.or_else(|| {
+                self.monitors.iter()
+                    .next()
+                    .map(|(i, _)| MonitorId { inner: i })
+            });
+        }
+
+        let moved = match self.focus {
+            Some(focus) => {
+                self[focus].tree.graft(mon.tree)
+                    .into_iter()
+                    .map(|cid| (focus, cid))
+                    .collect()
+            }
+            None => vec![],
+        };
+
+        conn.push(Event::MonitorDisconnect(id, moved));
+
+        Ok(())
+    }
+
     pub fn update<T>(&mut self, conn: &mut Connection<T>) -> Result<(), Error> {
         /* get all connected monitors, with the index of the primary monitor */
         let (monitors, primary) = monitors(&conn, self.root)?;
 
+        let names: Vec<_> = monitors.iter()
+            .map(|mon| mon.name.clone())
+            .collect();
+
         /* now iterate through the result, looking to pre-existing monitors */
         for (i, new) in monitors.into_iter().enumerate() {
             let mut added = true;
@@ -350,6 +745,16 @@ impl Display {
             }
         }
 
+        /* finally, remove any monitors which have been disconnected */
+        let removed: Vec<_> = self.monitors.iter()
+            .filter(|(_, mon)| !names.contains(&mon.name))
+            .map(|(index, _)| MonitorId { inner: index })
+            .collect();
+
+        for id in removed {
+            self.remove(conn, id)?;
+        }
+
         Ok(())
     }
 }
@@ -364,13 +769,140 @@ impl Display {
         output.client(client)
     }
 
+    /// Move a client into the focused layout of another monitor, keeping
+    /// its tag masks. Returns the id of the client on the new monitor.
+    pub fn move_client(&mut self, from: MonitorId, id: ClientId, to: MonitorId) -> ClientId {
+        if from == to {
+            return id;
+        }
+
+        let mut client = match self[from].remove(id) {
+            Window::Client(client) => client,
+            Window::Layout(_) => panic!("Display: invalid client id"),
+        };
+
+        let monitor = &mut self[to];
+        let layout = monitor.focused_layout();
+
+        client.set_border(monitor.borders.width);
+        monitor.tree.client(layout, client)
+    }
+
+    /// Swap the places of two clients, which may be on different monitors,
+    /// and return where the first client ends up. Focus follows the first
+    /// client.
+    pub fn swap_clients(&mut self, a: (MonitorId, ClientId), b: (MonitorId, ClientId)) -> (MonitorId, ClientId) {
+        if a.0 == b.0 {
+            self[a.0].tree.swap(a.1, b.1);
+            return a;
+        }
+
+        let (first, second) = self.monitors.get2_mut(a.0.inner, b.0.inner)
+            .expect("Display: invalid monitor id");
+
+        /* swap the clients themselves, leaving both trees as they are */
+        std::mem::swap(&mut first.tree[a.1], &mut second.tree[b.1]);
+
+        first.focus = Some(a.1);
+        second.focus = Some(b.1);
+        self.focus = Some(b.0);
+
+        b
+    }
+
+    /// Find the visible client nearest the focused one in a direction. When
+    /// there is none on the focused monitor, look on the nearest monitor in
+    /// that direction instead.
+    pub fn neighbour(&self, direction: Direction) -> Option<(MonitorId, ClientId)> {
+        let mid = self.focus?;
+        let monitor = &self[mid];
+        let focus = monitor.focus;
+
+        let from = match focus {
+            Some(cid) => *monitor[cid].rect(),
+            None => monitor.rect,
+        };
+
+        /* client ids only mean anything within their own monitor, so the
+         * focused client is only skipped on the monitor it is on */
+        let visible = |monitor: &Monitor, skip: Option<ClientId>| -> Vec<(ClientId, Rect)> {
+            monitor.walk()
+                .filter(|(cid, client)| client.visible() && Some(*cid) != skip)
+                .map(|(cid, client)| (cid, *client.rect()))
+                .collect()
+        };
+
+        if let Some(cid) = from.nearest(direction, visible(monitor, focus)) {
+            return Some((mid, cid));
+        }
+
+        /* spill over onto the next monitor */
+        let monitors: Vec<_> = self.iter()
+            .filter(|(id, _)| *id != mid)
+            .map(|(id, monitor)| (id, monitor.rect))
+            .collect();
+
+        let target = monitor.rect.nearest(direction, monitors)?;
+        let monitor = &self[target];
+        let clients = visible(monitor, None);
+
+        /* prefer the client lined up with the one we came from, then the
+         * one last focused there */
+        from.nearest(direction, clients.iter().copied())
+            .or_else(|| {
+                monitor.focus.filter(|cid| clients.iter().any(|(id, _)| id == cid))
+            })
+            .or_else(|| clients.first().map(|(cid, _)| *cid))
+            .map(|cid| (target, cid))
+    }
+
+    /// Focus a client, which also makes its monitor the focused monitor
     pub fn set_focus<T>(&mut self, conn: &mut Connection<T>, id: MonitorId, client: ClientId) -> Result<(), Error> {
+        self.focus = Some(id);
+
         let mon = &mut self[id];
+        let previous = mon.focus.replace(client);
 
+        mon[client].set_urgent(false);
         mon[client].focus(conn)?;
-        mon.focus = Some(client);
+        mon.paint(conn, client)?;
 
-        Ok(())
+        match previous {
+            Some(previous) if previous != client => mon.paint(conn, previous),
+            _ => Ok(()),
+        }
+    }
+
+    #[inline]
+    pub fn borders(&self) -> &Borders {
+        &self.borders
+    }
+
+    /// Set the gaps of every monitor, including those connected later
+    pub fn set_gaps(&mut self, gaps: Gaps) {
+        self.gaps = gaps;
+
+        for (_, mon) in self.monitors.iter_mut() {
+            mon.gaps = gaps;
+        }
+    }
+
+    /// Change the borders of all clients. This takes effect the next time
+    /// each monitor is arranged.
+    pub fn set_borders(&mut self, borders: Borders) {
+        self.borders = borders;
+
+        for (_, mon) in self.monitors.iter_mut() {
+            mon.borders = borders;
+
+            let ids: Vec<_> = mon.tree.clients()
+                .map(|(id, _)| id)
+                .collect();
+
+            for id in ids {
+                mon.tree[id].set_border(borders.width);
+            }
+        }
     }
 
     #[inline]
@@ -378,6 +910,11 @@ impl Display {
         self.focus
     }
 
+    #[inline]
+    pub fn get_primary(&self) -> Option<MonitorId> {
+        self.primary
+    }
+
     #[inline]
     pub fn iter(&self) -> Iter<'_> {
         Iter {
@@ -439,3 +976,89 @@ impl<'a> Iterator for IterMut<'a> {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::Monacle;
+    use crate::tag::{Tag, TagSet, Tags};
+
+    fn window(n: u32) -> x::Window {
+        unsafe { xcb::XidNew::new(n) }
+    }
+
+    fn display() -> Display {
+        let borders = Borders {
+            width: 0,
+            focus: 0,
+            unfocus: 0,
+            urgent: 0,
+            floating: 0,
+            smart: false,
+        };
+
+        Display::create(window(1), borders)
+    }
+
+    #[test]
+    fn test_neighbour_monitor() {
+        let mut display = display();
+
+        let left = display.insert(Monitor::create(window(1), String::from("left"), true, Rect::new(0, 0, 100, 100)));
+        let right = display.insert(Monitor::create(window(1), String::from("right"), false, Rect::new(100, 0, 100, 100)));
+
+        /* both monitors hold one client, so they share a client id */
+        let a = display[left].client(Client::mapped(window(2), Rect::new(0, 0, 100, 100)));
+        let b = display[right].client(Client::mapped(window(3), Rect::new(100, 0, 100, 100)));
+        assert_eq!(a, b);
+
+        display[left].focus = Some(a);
+        assert_eq!(display.get_focus(), Some(left));
+
+        assert_eq!(display.neighbour(Direction::Right), Some((right, b)));
+        assert_eq!(display.neighbour(Direction::Left), None);
+    }
+
+    #[test]
+    fn test_restore() {
+        let mut monitor = Monitor::create(window(1), String::from("main"), true, Rect::new(0, 0, 100, 100));
+        let mut tags = Tags::new();
+        let id = tags.insert(TagSet::new(&["a", "b"]));
+        let ids = [id];
+
+        tags[id].mask_mut().set(Tag::On(0));
+        let first = tags.select(&ids).view();
+        tags[id].mask_mut().set(Tag::On(1));
+        let second = tags.select(&ids).view();
+
+        monitor.restore(first.clone());
+        monitor.set_layout(monitor.tree.root(), Box::new(Monacle::new()));
+
+        /* a new view starts over, and going back brings the layout back */
+        monitor.restore(second.clone());
+        assert_eq!(monitor.symbol(), "[]=");
+        monitor.restore(first.clone());
+        assert_eq!(monitor.symbol(), "[M]");
+
+        /* views of a tagset which is gone are not kept */
+        monitor.restore(second);
+        monitor.forget(id);
+        monitor.restore(first);
+        assert_eq!(monitor.symbol(), "[]=");
+    }
+
+    #[test]
+    fn test_restore_limit() {
+        let mut monitor = Monitor::create(window(1), String::from("main"), true, Rect::new(0, 0, 100, 100));
+        let mut tags = Tags::new();
+        let ids: Vec<_> = (0..Monitor::VIEWS + 2)
+            .map(|_| tags.insert(TagSet::new(&["a"])))
+            .collect();
+
+        for id in ids.iter() {
+            monitor.restore(tags.select(&[*id]).view());
+        }
+
+        assert_eq!(monitor.layouts.len(), Monitor::VIEWS);
+    }
+}
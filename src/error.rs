@@ -8,6 +8,10 @@ pub enum Error {
     MissingScreen,
     #[error("invalid monitor")]
     MissingMonitor,
+    #[error("invalid restart state")]
+    InvalidState,
+    #[error("invalid color: {0}")]
+    InvalidColor(String),
     #[error("failed to register signal handler")]
     SignalError(std::io::Error),
     #[error("failed to connect to X11 server")]
@@ -19,3 +23,16 @@ pub enum Error {
     #[error("protocol error")]
     ProtocolError(#[from] xcb::ProtocolError),
 }
+
+impl Error {
+    /// Check whether the error is a BadWindow, which a request to a client
+    /// gets when the client has gone away before we heard about it
+    pub fn is_bad_window(&self) -> bool {
+        match self {
+            Error::XCBError(xcb::Error::Protocol(e)) | Error::ProtocolError(e) => {
+                matches!(e, xcb::ProtocolError::X(xcb::x::Error::Window(_), _))
+            }
+            _ => false,
+        }
+    }
+}
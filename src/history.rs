@@ -0,0 +1,107 @@
+/// Keys ordered by when they were last used, most recent first. While the
+/// history is frozen it ignores uses, so it can be walked without being
+/// reordered along the way.
+#[derive(Debug)]
+pub struct History<K> {
+    entries: Vec<K>,
+    frozen: bool,
+}
+
+impl<K: Copy + PartialEq> History<K> {
+    pub fn new() -> Self {
+        History {
+            entries: vec![],
+            frozen: false,
+        }
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Get the nth most recently used key, where 0 is the most recent
+    #[inline]
+    pub fn get(&self, n: usize) -> Option<K> {
+        self.entries.get(n).copied()
+    }
+
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = &K> {
+        self.entries.iter()
+    }
+
+    /// Move a key to the front of the history, adding it if it is new
+    pub fn touch(&mut self, key: K) {
+        if self.frozen {
+            return;
+        }
+
+        self.entries.retain(|k| *k != key);
+        self.entries.insert(0, key);
+    }
+
+    /// Forget a key. This applies even while frozen.
+    pub fn remove(&mut self, key: K) {
+        self.entries.retain(|k| *k != key);
+    }
+
+    #[inline]
+    pub fn freeze(&mut self) {
+        self.frozen = true;
+    }
+
+    #[inline]
+    pub fn thaw(&mut self) {
+        self.frozen = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(history: &History<u32>) -> Vec<u32> {
+        history.iter().copied().collect()
+    }
+
+    #[test]
+    fn test_touch() {
+        let mut history = History::new();
+
+        history.touch(1);
+        history.touch(2);
+        history.touch(3);
+        assert_eq!(keys(&history), vec![3, 2, 1]);
+
+        /* using an old key brings it to the front without duplicating it */
+        history.touch(1);
+        assert_eq!(keys(&history), vec![1, 3, 2]);
+        assert_eq!(history.get(1), Some(3));
+        assert_eq!(history.get(3), None);
+
+        history.remove(3);
+        assert_eq!(keys(&history), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_freeze() {
+        let mut history = History::new();
+
+        history.touch(1);
+        history.touch(2);
+        history.touch(3);
+
+        history.freeze();
+        history.touch(1);
+        assert_eq!(keys(&history), vec![3, 2, 1]);
+
+        /* removals still apply, a destroyed window is gone either way */
+        history.remove(2);
+        assert_eq!(keys(&history), vec![3, 1]);
+
+        history.thaw();
+        history.touch(1);
+        assert_eq!(keys(&history), vec![1, 3]);
+    }
+}
@@ -269,22 +269,10 @@ impl<T: Copy> Keys<T> {
                     self.grab(conn, Modifier::ANY, kc);
                 }
                 _ => {
-                    let mut cookie = self.grab(conn, binding.mask, kc);
-                    cookies.push(cookie);
-                    cookie = self.grab(conn, binding.mask | self.num_lock, kc);
-                    cookies.push(cookie);
-                    cookie = self.grab(conn, binding.mask | self.caps_lock, kc);
-                    cookies.push(cookie);
-                    cookie = self.grab(conn, binding.mask | self.scroll_lock, kc);
-                    cookies.push(cookie);
-                    cookie = self.grab(conn, binding.mask | self.caps_lock | self.num_lock, kc);
-                    cookies.push(cookie);
-                    cookie = self.grab(conn, binding.mask | self.scroll_lock | self.num_lock, kc);
-                    cookies.push(cookie);
-                    cookie = self.grab(conn, binding.mask | self.scroll_lock | self.caps_lock, kc);
-                    cookies.push(cookie);
-                    cookie = self.grab(conn, binding.mask | self.num_lock | self.scroll_lock | self.caps_lock, kc);
-                    cookies.push(cookie);
+                    for modifiers in self.locked(binding.mask) {
+                        let cookie = self.grab(conn, modifiers, kc);
+                        cookies.push(cookie);
+                    }
                 }
             }
         }
@@ -296,6 +284,121 @@ impl<T: Copy> Keys<T> {
         Ok(())
     }
 
+    /// Drop every binding local to a monitor, after it is disconnected.
+    /// Keys left without any binding are ungrabbed.
+    pub fn unbind(&mut self, conn: &mut Connection<T>, monitor: MonitorId) -> Result<(), Error> {
+        for set in self.bindings.values_mut() {
+            set.local.retain(|x| x.monitor != Some(monitor));
+        }
+
+        let empty: Vec<_> = self.bindings.iter()
+            .filter(|(_, set)| set.local.is_empty() && set.global.is_none())
+            .map(|(key, _)| *key)
+            .collect();
+
+        for key in empty.iter() {
+            self.bindings.remove(key);
+        }
+
+        let mut cookies = vec![];
+
+        for (mask, kc, press) in empty {
+            /* the grab is shared by the press and release of a key */
+            if self.bindings.contains_key(&(mask, kc, !press)) {
+                continue;
+            }
+
+            let modifiers = match mask {
+                Modifier::ANY => vec![Modifier::ANY],
+                _ => self.locked(mask).to_vec(),
+            };
+
+            for modifiers in modifiers {
+                let m = unsafe { x::ModMask::from_bits_unchecked(modifiers.bits()) };
+
+                cookies.push(conn.send_request_checked(&x::UngrabKey {
+                    key: kc,
+                    grab_window: self.root,
+                    modifiers: m,
+                }));
+            }
+        }
+
+        for cookie in cookies {
+            conn.check_request(cookie)?;
+        }
+
+        Ok(())
+    }
+
+    /// Get a modifier mask combined with every combination of locks, which
+    /// are ignored when matching bindings but still need their own grabs
+    fn locked(&self, mask: Modifier) -> [Modifier; 8] {
+        [
+            mask,
+            mask | self.num_lock,
+            mask | self.caps_lock,
+            mask | self.scroll_lock,
+            mask | self.caps_lock | self.num_lock,
+            mask | self.scroll_lock | self.num_lock,
+            mask | self.scroll_lock | self.caps_lock,
+            mask | self.num_lock | self.scroll_lock | self.caps_lock,
+        ]
+    }
+
+    /// Grab the whole keyboard while a modifier is held, so that its
+    /// release reaches the release bindings wherever focus is. Returns
+    /// false if the keyboard could not be grabbed, or the modifier is
+    /// already up, in which case nothing is left grabbed.
+    pub fn grab_keyboard(&self, conn: &mut Connection<T>, modifier: Modifier) -> Result<bool, Error> {
+        let cookie = conn.send_request(&x::GrabKeyboard {
+            owner_events: false,
+            grab_window: self.root,
+            time: x::CURRENT_TIME,
+            pointer_mode: x::GrabMode::Async,
+            keyboard_mode: x::GrabMode::Async,
+        });
+
+        if conn.wait_for_reply(cookie)?.status() != x::GrabStatus::Success {
+            return Ok(false);
+        }
+
+        /* the modifier may have been released before the grab */
+        if !self.held(conn, modifier)? {
+            self.ungrab_keyboard(conn)?;
+            return Ok(false);
+        }
+
+        Ok(true)
+    }
+
+    pub fn ungrab_keyboard(&self, conn: &mut Connection<T>) -> Result<(), Error> {
+        conn.send_and_check_request(&x::UngrabKeyboard {
+            time: x::CURRENT_TIME,
+        })?;
+
+        Ok(())
+    }
+
+    /// Check whether a modifier is currently down
+    fn held(&self, conn: &mut Connection<T>, modifier: Modifier) -> Result<bool, Error> {
+        let cookie = conn.send_request(&x::QueryPointer {
+            window: self.root,
+        });
+
+        let reply = conn.wait_for_reply(cookie)?;
+
+        Ok(self.modifiers(reply.mask()).intersects(modifier))
+    }
+
+    /// Convert the state of an input event to modifiers, ignoring locks
+    pub fn modifiers(&self, mask: x::KeyButMask) -> Modifier {
+        let mut modifiers = unsafe { Modifier::from_bits_unchecked(mask.bits()) };
+        modifiers.remove(self.num_lock | self.caps_lock | self.scroll_lock);
+
+        modifiers
+    }
+
     pub fn get(
         &self,
         focus: Option<MonitorId>,
@@ -303,8 +406,7 @@ impl<T: Copy> Keys<T> {
         k: Keycode,
         press: bool,
     ) -> Option<T> {
-        let mut modifiers = unsafe { Modifier::from_bits_unchecked(mask.bits()) };
-        modifiers.remove(self.num_lock | self.caps_lock | self.scroll_lock);
+        let modifiers = self.modifiers(mask);
 
         self.bindings
             .get(&(modifiers, k, press))
@@ -1,4 +1,4 @@
-use crate::rect::{Cut, Rect, Split};
+use crate::rect::{Cut, Rect, Split, SplitIterator};
 
 #[derive(Debug)]
 pub enum Cell {
@@ -7,8 +7,141 @@ pub enum Cell {
     Focus(Rect),
 }
 
+impl Cell {
+    /// Move a cell to a new rect, keeping it focused if it was
+    pub fn place(&mut self, rect: Rect) {
+        *self = match self {
+            Cell::Focus(_) => Cell::Focus(rect),
+            _ => Cell::Show(rect),
+        };
+    }
+}
+
+/// Space left around clients when they are arranged
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Gaps {
+    /// Space between neighbouring clients
+    pub inner: u16,
+    /// Space between clients and the edge of the monitor
+    pub outer: u16,
+    /// Disable gaps while only a single client is visible
+    pub smart: bool,
+}
+
+/* limits for the fraction of an area a layout gives to its first part */
+const MIN_FACTOR: f32 = 0.05;
+const MAX_FACTOR: f32 = 0.95;
+
+/// A command sent to a layout at runtime. Layouts ignore messages which
+/// do not apply to them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LayoutMessage {
+    /// Add to the number of master windows
+    IncMaster(i32),
+    /// Set the fraction of the area given to the masters
+    SetFactor(f32),
+    /// Add to the fraction of the area given to the masters
+    IncFactor(f32),
+    /// Add to the number of columns in the stack
+    IncColumns(i32),
+}
+
 pub trait Layout {
     fn arrange(&mut self, scope: &Rect, cells: &mut [Cell]);
+
+    /// A short symbol for the layout, for display in a bar
+    fn symbol(&self) -> &str;
+
+    /// Handle a message, returning true if the layout changed and needs
+    /// to be rearranged
+    fn handle(&mut self, _msg: LayoutMessage) -> bool {
+        false
+    }
+
+    /// Receive the titles of the layout's children before it is arranged,
+    /// in the same order as the cells. Only layouts which draw titles need
+    /// to keep them.
+    fn titles(&mut self, _titles: Vec<String>) {
+    }
+
+    /// Receive ids for the layout's children before it is arranged, in
+    /// the same order as the cells. Ids stay the same while children come
+    /// and go, so layouts which group children can follow them.
+    fn children(&mut self, _children: &[usize]) {
+    }
+
+    /// Get the titles to draw for the last arrangement, if any
+    fn decoration(&self) -> Option<&Decoration> {
+        None
+    }
+
+    /// Whether the layout should be replaced by its child once it only
+    /// has a single child left
+    fn collapse(&self) -> bool {
+        false
+    }
+}
+
+/// A strip of titles which a layout reserves inside its scope, with one
+/// tab for each child
+#[derive(Debug, Clone, PartialEq)]
+pub struct Decoration {
+    /// The area of the strip, taken out of the layout's scope
+    pub rect: Rect,
+    /// Tabs are listed from top to bottom rather than left to right
+    pub vertical: bool,
+    pub tabs: Vec<Tab>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tab {
+    pub title: String,
+    /// The child is the one being shown
+    pub active: bool,
+}
+
+impl Decoration {
+    /// Split an area the strip is drawn into with one rect per tab
+    pub fn split<'a>(&self, area: &'a Rect) -> SplitIterator<'a> {
+        if self.vertical {
+            area.split(Split::Horizontal(self.tabs.len()))
+        } else {
+            area.split(Split::Vertical(self.tabs.len()))
+        }
+    }
+}
+
+/// A list of layouts to switch between
+pub struct Cycle {
+    layouts: Vec<fn() -> Box<dyn Layout>>,
+}
+
+impl Cycle {
+    pub fn new() -> Self {
+        Cycle {
+            layouts: vec![],
+        }
+    }
+
+    /// Add a layout to the end of the cycle
+    pub fn push(&mut self, layout: fn() -> Box<dyn Layout>) {
+        self.layouts.push(layout);
+    }
+
+    /// Create the layout after the current one. Layouts are told apart by
+    /// their symbol, and a layout which is not in the cycle is followed by
+    /// the first one.
+    pub fn next(&self, current: &dyn Layout) -> Option<Box<dyn Layout>> {
+        let position = self.layouts.iter()
+            .position(|layout| layout().symbol() == current.symbol());
+
+        let next = match position {
+            Some(i) => (i + 1) % self.layouts.len(),
+            None => 0,
+        };
+
+        self.layouts.get(next).map(|layout| layout())
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -30,6 +163,10 @@ impl Layout for Monacle {
             }
         }
     }
+
+    fn symbol(&self) -> &str {
+        "[M]"
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -47,6 +184,27 @@ impl LeftMaster {
             columns: 1,
         }
     }
+
+    #[inline]
+    pub fn factor(&self) -> f32 {
+        self.factor
+    }
+
+    #[inline]
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    #[inline]
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+}
+
+/// Add a signed amount to a count, keeping it at least one
+fn adjust(count: usize, delta: i32) -> usize {
+    let count = count as i64 + delta as i64;
+    count.max(1) as usize
 }
 
 impl Layout for LeftMaster {
@@ -56,11 +214,11 @@ impl Layout for LeftMaster {
 
         if count == 1 {
             /* one client -- full screen */
-            cells[0] = Cell::Show(*scope);
+            cells[0].place(*scope);
         } else if count <= self.rows {
             /* only enough windows for the masters */
             for master in scope.split(Split::Horizontal(count)) {
-                cells[index] = Cell::Show(master);
+                cells[index].place(master);
                 index += 1;
             }
         } else {
@@ -70,7 +228,7 @@ impl Layout for LeftMaster {
 
             /* resize master(s) */
             for master in left.split(Split::Horizontal(self.rows)) {
-                cells[index] = Cell::Show(master);
+                cells[index].place(master);
                 index += 1;
             }
 
@@ -97,10 +255,1231 @@ impl Layout for LeftMaster {
                 count -= rows;
 
                 for window in column.split(Split::Horizontal(rows)) {
-                    cells[index] = Cell::Show(window);
+                    cells[index].place(window);
                     index += 1;
                 }
             }
         }
     }
+
+    fn symbol(&self) -> &str {
+        "[]="
+    }
+
+    fn handle(&mut self, msg: LayoutMessage) -> bool {
+        let previous = (self.factor, self.rows, self.columns);
+
+        match msg {
+            LayoutMessage::IncMaster(n) => {
+                self.rows = adjust(self.rows, n);
+            }
+            LayoutMessage::SetFactor(_) | LayoutMessage::IncFactor(_) => {
+                self.factor = factor(self.factor, msg);
+            }
+            LayoutMessage::IncColumns(n) => {
+                self.columns = adjust(self.columns, n);
+            }
+        }
+
+        previous != (self.factor, self.rows, self.columns)
+    }
+}
+
+/// Handle a message for a layout with a master count and factor, which
+/// ignores stack columns
+fn master(masters: &mut usize, ratio: &mut f32, msg: LayoutMessage) -> bool {
+    let previous = (*masters, *ratio);
+
+    match msg {
+        LayoutMessage::IncMaster(n) => {
+            *masters = adjust(*masters, n);
+        }
+        LayoutMessage::SetFactor(_) | LayoutMessage::IncFactor(_) => {
+            *ratio = factor(*ratio, msg);
+        }
+        LayoutMessage::IncColumns(_) => {}
+    }
+
+    previous != (*masters, *ratio)
+}
+
+/// Masters stacked in a column in the middle, with the rest of the clients
+/// split between columns on either side
+#[derive(Debug, Clone)]
+pub struct CenteredMaster {
+    factor: f32,
+    masters: usize,
+}
+
+impl CenteredMaster {
+    pub fn new() -> Self {
+        CenteredMaster {
+            factor: 0.5,
+            masters: 1,
+        }
+    }
+
+    #[inline]
+    pub fn factor(&self) -> f32 {
+        self.factor
+    }
+
+    #[inline]
+    pub fn masters(&self) -> usize {
+        self.masters
+    }
+}
+
+impl Layout for CenteredMaster {
+    fn arrange(&mut self, scope: &Rect, cells: &mut [Cell]) {
+        let count = cells.len();
+
+        if count == 0 {
+            return;
+        }
+
+        if count <= self.masters {
+            /* only enough windows for the masters */
+            for (cell, master) in cells.iter_mut().zip(scope.split(Split::Horizontal(count))) {
+                cell.place(master);
+            }
+
+            return;
+        }
+
+        let (masters, stack) = cells.split_at_mut(self.masters);
+        let width = (scope.w as f32 * self.factor).round() as u16;
+
+        if stack.len() == 1 {
+            /* a single stack client goes to the right, as in LeftMaster */
+            let (left, right) = scope.cut(Cut::Vertical(width));
+
+            for (cell, master) in masters.iter_mut().zip(left.split(Split::Horizontal(self.masters))) {
+                cell.place(master);
+            }
+
+            stack[0].place(right);
+            return;
+        }
+
+        /* the sides share what is left of the width evenly */
+        let (left, rest) = scope.cut(Cut::Vertical((scope.w - width.min(scope.w)) / 2));
+        let (center, right) = rest.cut(Cut::Vertical(width));
+
+        for (cell, master) in masters.iter_mut().zip(center.split(Split::Horizontal(self.masters))) {
+            cell.place(master);
+        }
+
+        /* stack clients alternate between the sides, starting on the right */
+        let rights = stack.len().div_ceil(2);
+        let lefts = stack.len() / 2;
+
+        let mut right = right.split(Split::Horizontal(rights));
+        let mut left = left.split(Split::Horizontal(lefts));
+
+        for (i, cell) in stack.iter_mut().enumerate() {
+            let rect = if i % 2 == 0 {
+                right.next()
+            } else {
+                left.next()
+            };
+
+            if let Some(rect) = rect {
+                cell.place(rect);
+            }
+        }
+    }
+
+    fn symbol(&self) -> &str {
+        "|M|"
+    }
+
+    fn handle(&mut self, msg: LayoutMessage) -> bool {
+        master(&mut self.masters, &mut self.factor, msg)
+    }
+}
+
+/// Masters side by side across the top, with the rest of the clients side
+/// by side underneath
+#[derive(Debug, Clone)]
+pub struct BottomStack {
+    factor: f32,
+    masters: usize,
+}
+
+impl BottomStack {
+    pub fn new() -> Self {
+        BottomStack {
+            factor: 0.5,
+            masters: 1,
+        }
+    }
+
+    #[inline]
+    pub fn factor(&self) -> f32 {
+        self.factor
+    }
+
+    #[inline]
+    pub fn masters(&self) -> usize {
+        self.masters
+    }
+}
+
+impl Layout for BottomStack {
+    fn arrange(&mut self, scope: &Rect, cells: &mut [Cell]) {
+        let count = cells.len();
+
+        if count == 0 {
+            return;
+        }
+
+        if count <= self.masters {
+            /* only enough windows for the masters */
+            for (cell, master) in cells.iter_mut().zip(scope.split(Split::Vertical(count))) {
+                cell.place(master);
+            }
+
+            return;
+        }
+
+        let height = (scope.h as f32 * self.factor).round() as u16;
+        let (top, bottom) = scope.cut(Cut::Horizontal(height));
+        let (masters, stack) = cells.split_at_mut(self.masters);
+
+        for (cell, master) in masters.iter_mut().zip(top.split(Split::Vertical(self.masters))) {
+            cell.place(master);
+        }
+
+        let count = stack.len();
+
+        for (cell, window) in stack.iter_mut().zip(bottom.split(Split::Vertical(count))) {
+            cell.place(window);
+        }
+    }
+
+    fn symbol(&self) -> &str {
+        "TTT"
+    }
+
+    fn handle(&mut self, msg: LayoutMessage) -> bool {
+        master(&mut self.masters, &mut self.factor, msg)
+    }
+}
+
+/// Which way a grid grows when its cells cannot make a square
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Prefer {
+    Columns,
+    Rows,
+}
+
+#[derive(Debug, Clone)]
+pub struct Grid {
+    prefer: Prefer,
+}
+
+impl Grid {
+    /// Create a grid which prefers more columns on wide monitors
+    pub fn new() -> Self {
+        Grid {
+            prefer: Prefer::Columns,
+        }
+    }
+
+    /// Create a grid with a preference for its shape on wide monitors. Tall
+    /// monitors use the opposite.
+    pub fn prefer(prefer: Prefer) -> Self {
+        Grid {
+            prefer: prefer,
+        }
+    }
+
+    /// Get the number of columns and rows for a number of cells
+    fn shape(&self, scope: &Rect, count: usize) -> (usize, usize) {
+        /* the smallest square which fits every cell */
+        let mut major = 1;
+        while major * major < count {
+            major += 1;
+        }
+
+        let minor = count.div_ceil(major);
+
+        let wide = scope.w >= scope.h;
+
+        match (self.prefer, wide) {
+            (Prefer::Columns, true) | (Prefer::Rows, false) => (major, minor),
+            (Prefer::Rows, true) | (Prefer::Columns, false) => (minor, major),
+        }
+    }
+}
+
+impl Layout for Grid {
+    fn arrange(&mut self, scope: &Rect, cells: &mut [Cell]) {
+        let count = cells.len();
+
+        if count == 0 {
+            return;
+        }
+
+        let (columns, rows) = self.shape(scope, count);
+        let mut index = 0;
+
+        for (i, row) in scope.split(Split::Horizontal(rows)).enumerate() {
+            /* the last row spreads whatever is left across its width */
+            let n = if i + 1 == rows {
+                count - index
+            } else {
+                columns
+            };
+
+            for cell in row.split(Split::Vertical(n)) {
+                cells[index].place(cell);
+                index += 1;
+            }
+        }
+    }
+
+    fn symbol(&self) -> &str {
+        "###"
+    }
+}
+
+/// Apply a factor message, returning the new factor
+fn factor(factor: f32, msg: LayoutMessage) -> f32 {
+    match msg {
+        LayoutMessage::SetFactor(f) => f.clamp(MIN_FACTOR, MAX_FACTOR),
+        LayoutMessage::IncFactor(f) => (factor + f).clamp(MIN_FACTOR, MAX_FACTOR),
+        _ => factor,
+    }
+}
+
+/// Give each cell a fraction of the area left by the cells before it,
+/// alternating between vertical and horizontal cuts. A spiral turns
+/// clockwise, taking its cells from the left, top, right and then bottom
+/// of what remains, while otherwise cells always come from the left or top
+/// so the remaining area shrinks toward the bottom right.
+fn fibonacci(scope: &Rect, cells: &mut [Cell], factor: f32, spiral: bool) {
+    let count = cells.len();
+    let mut rest = *scope;
+
+    for (i, cell) in cells.iter_mut().enumerate() {
+        if i + 1 == count {
+            cell.place(rest);
+            break;
+        }
+
+        let vertical = i % 2 == 0;
+        let length = if vertical { rest.w } else { rest.h };
+        let take = (length as f32 * factor).round() as u16;
+
+        /* spirals take from the far side on every other pair of cuts */
+        let far = spiral && i % 4 >= 2;
+
+        let at = if far { length - take } else { take };
+        let (first, second) = if vertical {
+            rest.cut(Cut::Vertical(at))
+        } else {
+            rest.cut(Cut::Horizontal(at))
+        };
+
+        if far {
+            cell.place(second);
+            rest = first;
+        } else {
+            cell.place(first);
+            rest = second;
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Spiral {
+    factor: f32,
+}
+
+impl Spiral {
+    pub fn new() -> Self {
+        Spiral::with_factor(0.5)
+    }
+
+    /// Create a spiral where each cell takes a fraction of the remaining area
+    pub fn with_factor(factor: f32) -> Self {
+        Spiral {
+            factor: factor.clamp(MIN_FACTOR, MAX_FACTOR),
+        }
+    }
+}
+
+impl Layout for Spiral {
+    fn arrange(&mut self, scope: &Rect, cells: &mut [Cell]) {
+        fibonacci(scope, cells, self.factor, true);
+    }
+
+    fn symbol(&self) -> &str {
+        "[@]"
+    }
+
+    fn handle(&mut self, msg: LayoutMessage) -> bool {
+        let previous = self.factor;
+        self.factor = factor(self.factor, msg);
+
+        previous != self.factor
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Dwindle {
+    factor: f32,
+}
+
+impl Dwindle {
+    pub fn new() -> Self {
+        Dwindle::with_factor(0.5)
+    }
+
+    /// Create a dwindle where each cell takes a fraction of the remaining area
+    pub fn with_factor(factor: f32) -> Self {
+        Dwindle {
+            factor: factor.clamp(MIN_FACTOR, MAX_FACTOR),
+        }
+    }
+}
+
+impl Layout for Dwindle {
+    fn arrange(&mut self, scope: &Rect, cells: &mut [Cell]) {
+        fibonacci(scope, cells, self.factor, false);
+    }
+
+    fn symbol(&self) -> &str {
+        "[\\]"
+    }
+
+    fn handle(&mut self, msg: LayoutMessage) -> bool {
+        let previous = self.factor;
+        self.factor = factor(self.factor, msg);
+
+        previous != self.factor
+    }
+}
+
+/* state shared by tabbed and stacked layouts, which show a single child
+ * below a strip of titles */
+#[derive(Debug, Clone)]
+struct Titled {
+    /* height of a single title */
+    height: u16,
+    /* the child shown while focus is outside the layout */
+    selected: usize,
+    titles: Vec<String>,
+    decoration: Option<Decoration>,
+}
+
+impl Titled {
+    fn new(height: u16) -> Self {
+        Titled {
+            height: height,
+            selected: 0,
+            titles: vec![],
+            decoration: None,
+        }
+    }
+
+    fn arrange(&mut self, scope: &Rect, cells: &mut [Cell], vertical: bool) {
+        if cells.is_empty() {
+            self.decoration = None;
+            return;
+        }
+
+        /* follow focus, and otherwise keep showing the last child focused */
+        if let Some(i) = cells.iter().position(|cell| matches!(cell, Cell::Focus(_))) {
+            self.selected = i;
+        }
+
+        self.selected = self.selected.min(cells.len() - 1);
+
+        let height = if vertical {
+            self.height.saturating_mul(cells.len().min(u16::MAX as usize) as u16)
+        } else {
+            self.height
+        };
+
+        let (strip, body) = scope.cut(Cut::Horizontal(height));
+
+        for (i, cell) in cells.iter_mut().enumerate() {
+            if i == self.selected {
+                cell.place(body);
+            } else {
+                *cell = Cell::Hide;
+            }
+        }
+
+        let tabs = (0..cells.len())
+            .map(|i| Tab {
+                title: self.titles.get(i).cloned().unwrap_or_default(),
+                active: i == self.selected,
+            })
+            .collect();
+
+        self.decoration = Some(Decoration {
+            rect: strip,
+            vertical: vertical,
+            tabs: tabs,
+        });
+    }
+}
+
+/// Show only the selected child, with the titles of all children side by
+/// side in a strip across the top
+#[derive(Debug, Clone)]
+pub struct Tabbed {
+    titled: Titled,
+}
+
+impl Tabbed {
+    pub fn new() -> Self {
+        Tabbed::with_height(15)
+    }
+
+    /// Create a tabbed layout with a strip of a given height
+    pub fn with_height(height: u16) -> Self {
+        Tabbed {
+            titled: Titled::new(height),
+        }
+    }
+}
+
+impl Layout for Tabbed {
+    fn arrange(&mut self, scope: &Rect, cells: &mut [Cell]) {
+        self.titled.arrange(scope, cells, false);
+    }
+
+    fn symbol(&self) -> &str {
+        "[T]"
+    }
+
+    fn titles(&mut self, titles: Vec<String>) {
+        self.titled.titles = titles;
+    }
+
+    fn decoration(&self) -> Option<&Decoration> {
+        self.titled.decoration.as_ref()
+    }
+}
+
+/// Show only the selected child, below a list of the titles of all
+/// children with one per row
+#[derive(Debug, Clone)]
+pub struct Stacked {
+    titled: Titled,
+}
+
+impl Stacked {
+    pub fn new() -> Self {
+        Stacked::with_height(15)
+    }
+
+    /// Create a stacked layout where each title row has a given height
+    pub fn with_height(height: u16) -> Self {
+        Stacked {
+            titled: Titled::new(height),
+        }
+    }
+}
+
+impl Layout for Stacked {
+    fn arrange(&mut self, scope: &Rect, cells: &mut [Cell]) {
+        self.titled.arrange(scope, cells, true);
+    }
+
+    fn symbol(&self) -> &str {
+        "[S]"
+    }
+
+    fn titles(&mut self, titles: Vec<String>) {
+        self.titled.titles = titles;
+    }
+
+    fn decoration(&self) -> Option<&Decoration> {
+        self.titled.decoration.as_ref()
+    }
+}
+
+/// Two children side by side or one above the other, created around a
+/// client when it is split in two
+#[derive(Debug, Clone)]
+pub struct BinarySplit {
+    factor: f32,
+    vertical: bool,
+}
+
+impl BinarySplit {
+    /// Create a split with children side by side
+    pub fn horizontal() -> Self {
+        BinarySplit {
+            factor: 0.5,
+            vertical: false,
+        }
+    }
+
+    /// Create a split with children one above the other
+    pub fn vertical() -> Self {
+        BinarySplit {
+            factor: 0.5,
+            vertical: true,
+        }
+    }
+
+    #[inline]
+    pub fn factor(&self) -> f32 {
+        self.factor
+    }
+
+    #[inline]
+    pub fn is_vertical(&self) -> bool {
+        self.vertical
+    }
+}
+
+impl Layout for BinarySplit {
+    fn arrange(&mut self, scope: &Rect, cells: &mut [Cell]) {
+        let count = cells.len();
+
+        if count == 2 {
+            let (first, second) = if self.vertical {
+                let height = scope.h as f32 * self.factor;
+                scope.cut(Cut::Horizontal(height.round() as u16))
+            } else {
+                let width = scope.w as f32 * self.factor;
+                scope.cut(Cut::Vertical(width.round() as u16))
+            };
+
+            cells[0].place(first);
+            cells[1].place(second);
+        } else {
+            /* only left with more children by moving them in by hand */
+            let split = if self.vertical {
+                Split::Horizontal(count)
+            } else {
+                Split::Vertical(count)
+            };
+
+            for (cell, rect) in cells.iter_mut().zip(scope.split(split)) {
+                cell.place(rect);
+            }
+        }
+    }
+
+    fn symbol(&self) -> &str {
+        if self.vertical {
+            "[-]"
+        } else {
+            "[|]"
+        }
+    }
+
+    fn handle(&mut self, msg: LayoutMessage) -> bool {
+        let previous = self.factor;
+        self.factor = factor(self.factor, msg);
+
+        previous != self.factor
+    }
+
+    fn collapse(&self) -> bool {
+        true
+    }
+}
+
+/* a column of a scrolling layout */
+#[derive(Debug, Clone)]
+struct Column {
+    /* fraction of the viewport width */
+    width: f32,
+    /* ids of the children in the column, in the order of the cells */
+    children: Vec<usize>,
+}
+
+/// An endless strip of columns, each with its own width, of which only
+/// the columns around the focused one fit in view. The view scrolls just
+/// far enough to keep the focused column fully visible.
+#[derive(Debug, Clone)]
+pub struct Scrolling {
+    /* width given to new columns */
+    width: f32,
+    columns: Vec<Column>,
+    /* ids of the cells about to be arranged */
+    children: Vec<usize>,
+    /* the column kept in view while focus is outside the layout */
+    focused: usize,
+    /* distance of the view from the start of the strip, in pixels */
+    offset: u32,
+}
+
+impl Scrolling {
+    pub fn new() -> Self {
+        Scrolling::with_width(0.5)
+    }
+
+    /// Create a scrolling layout where new columns take a fraction of
+    /// the view's width
+    pub fn with_width(width: f32) -> Self {
+        Scrolling {
+            width: width.clamp(MIN_FACTOR, MAX_FACTOR),
+            columns: vec![],
+            children: vec![],
+            focused: 0,
+            offset: 0,
+        }
+    }
+
+    #[inline]
+    pub fn offset(&self) -> u32 {
+        self.offset
+    }
+
+    /// Match the columns to the children being arranged. Children which
+    /// are gone leave their columns, and new children each get a column
+    /// at the end of the strip.
+    fn fit(&mut self, ids: &[usize]) {
+        let position = |id: &usize| ids.iter().position(|x| x == id);
+
+        for column in self.columns.iter_mut() {
+            column.children.retain(|id| position(id).is_some());
+            column.children.sort_by_key(|id| position(id));
+        }
+
+        self.columns.retain(|column| !column.children.is_empty());
+
+        for id in ids.iter() {
+            let placed = self.columns.iter()
+                .any(|column| column.children.contains(id));
+
+            if !placed {
+                self.columns.push(Column {
+                    width: self.width,
+                    children: vec![*id],
+                });
+            }
+        }
+    }
+
+    /// Move clients between the focused column and the one after it
+    fn consume(&mut self, delta: i32) -> bool {
+        let focused = self.focused;
+        let mut changed = false;
+
+        for _ in 0..delta.unsigned_abs() {
+            if delta > 0 {
+                /* take the first client of the next column */
+                if focused + 1 >= self.columns.len() {
+                    break;
+                }
+
+                let id = self.columns[focused + 1].children.remove(0);
+                self.columns[focused].children.push(id);
+
+                if self.columns[focused + 1].children.is_empty() {
+                    self.columns.remove(focused + 1);
+                }
+            } else {
+                /* give the last client a column of its own */
+                if self.columns[focused].children.len() <= 1 {
+                    break;
+                }
+
+                let id = self.columns[focused].children.pop().unwrap();
+                self.columns.insert(focused + 1, Column {
+                    width: self.width,
+                    children: vec![id],
+                });
+            }
+
+            changed = true;
+        }
+
+        changed
+    }
+}
+
+impl Layout for Scrolling {
+    fn arrange(&mut self, scope: &Rect, cells: &mut [Cell]) {
+        /* without ids from the tree, cells are known by their position */
+        let mut ids = std::mem::take(&mut self.children);
+
+        if ids.len() != cells.len() {
+            ids = (0..cells.len()).collect();
+        }
+
+        self.fit(&ids);
+
+        if self.columns.is_empty() {
+            self.focused = 0;
+            self.offset = 0;
+            return;
+        }
+
+        /* find the column holding the focused cell */
+        if let Some(i) = cells.iter().position(|cell| matches!(cell, Cell::Focus(_))) {
+            if let Some(j) = self.columns.iter().position(|column| column.children.contains(&ids[i])) {
+                self.focused = j;
+            }
+        }
+
+        self.focused = self.focused.min(self.columns.len() - 1);
+
+        let view = scope.w as u32;
+        let widths: Vec<u32> = self.columns.iter()
+            .map(|column| ((view as f32 * column.width).round() as u32).max(1))
+            .collect();
+
+        let start: u32 = widths[..self.focused].iter().sum();
+        let end = start + widths[self.focused];
+        let total: u32 = widths.iter().sum();
+
+        /* scroll as little as possible to show the focused column */
+        if start < self.offset {
+            self.offset = start;
+        } else if end > self.offset + view {
+            self.offset = end - view;
+        }
+
+        self.offset = self.offset.min(total.saturating_sub(view));
+
+        let mut x = 0;
+
+        for (column, width) in self.columns.iter().zip(widths) {
+            let indices = column.children.iter()
+                .filter_map(|id| ids.iter().position(|x| x == id));
+
+            /* only columns which fit entirely in view are shown */
+            if x >= self.offset && x + width <= self.offset + view {
+                let left = scope.x as i32 + (x - self.offset) as i32;
+                let rect = Rect::new(left as i16, scope.y, width as u16, scope.h);
+                let count = column.children.len();
+
+                for (i, r) in indices.zip(rect.split(Split::Horizontal(count))) {
+                    cells[i].place(r);
+                }
+            } else {
+                for i in indices {
+                    cells[i] = Cell::Hide;
+                }
+            }
+
+            x += width;
+        }
+    }
+
+    fn symbol(&self) -> &str {
+        "[>]"
+    }
+
+    fn children(&mut self, children: &[usize]) {
+        self.children = children.to_vec();
+    }
+
+    /// The factor is the width of the focused column, and the master count
+    /// moves clients into or out of it
+    fn handle(&mut self, msg: LayoutMessage) -> bool {
+        if self.columns.is_empty() {
+            return false;
+        }
+
+        match msg {
+            LayoutMessage::IncMaster(n) => {
+                self.consume(n)
+            }
+            LayoutMessage::SetFactor(_) | LayoutMessage::IncFactor(_) => {
+                let column = &mut self.columns[self.focused];
+                let previous = column.width;
+                column.width = factor(column.width, msg);
+
+                previous != column.width
+            }
+            LayoutMessage::IncColumns(_) => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rects(cells: &[Cell]) -> Vec<Rect> {
+        cells.iter()
+            .filter_map(|cell| match cell {
+                Cell::Show(r) | Cell::Focus(r) => Some(*r),
+                Cell::Hide => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_left_master_messages() {
+        let mut layout = LeftMaster::new();
+
+        assert!(layout.handle(LayoutMessage::IncMaster(-1)));
+        assert_eq!(layout.rows(), 1);
+
+        /* there is always at least one master */
+        assert!(!layout.handle(LayoutMessage::IncMaster(-1)));
+        assert_eq!(layout.rows(), 1);
+
+        assert!(layout.handle(LayoutMessage::IncFactor(0.1)));
+        assert!((layout.factor() - 0.6).abs() < f32::EPSILON);
+
+        assert!(layout.handle(LayoutMessage::SetFactor(2.0)));
+        assert_eq!(layout.factor(), MAX_FACTOR);
+
+        assert!(layout.handle(LayoutMessage::IncColumns(1)));
+        assert_eq!(layout.columns(), 2);
+    }
+
+    #[test]
+    fn test_cycle() {
+        let mut cycle = Cycle::new();
+        assert!(cycle.next(&Monacle::new()).is_none());
+
+        cycle.push(|| Box::new(LeftMaster::new()));
+        cycle.push(|| Box::new(Monacle::new()));
+
+        assert_eq!(cycle.next(&LeftMaster::new()).unwrap().symbol(), "[M]");
+        assert_eq!(cycle.next(&Monacle::new()).unwrap().symbol(), "[]=");
+    }
+
+    #[test]
+    fn test_grid() {
+        let scope = Rect::new(0, 0, 90, 60);
+        let mut cells: Vec<Cell> = (0..5).map(|_| Cell::Hide).collect();
+
+        Grid::new().arrange(&scope, &mut cells);
+
+        assert_eq!(rects(&cells), vec![
+            Rect::new(0, 0, 30, 30),
+            Rect::new(30, 0, 30, 30),
+            Rect::new(60, 0, 30, 30),
+            Rect::new(0, 30, 45, 30),
+            Rect::new(45, 30, 45, 30),
+        ]);
+
+        Grid::prefer(Prefer::Rows).arrange(&scope, &mut cells);
+
+        assert_eq!(rects(&cells), vec![
+            Rect::new(0, 0, 45, 20),
+            Rect::new(45, 0, 45, 20),
+            Rect::new(0, 20, 45, 20),
+            Rect::new(45, 20, 45, 20),
+            Rect::new(0, 40, 90, 20),
+        ]);
+    }
+
+    #[test]
+    fn test_grid_square() {
+        let scope = Rect::new(0, 0, 100, 100);
+        let mut cells: Vec<Cell> = (0..4).map(|_| Cell::Hide).collect();
+
+        Grid::new().arrange(&scope, &mut cells);
+
+        assert_eq!(rects(&cells), vec![
+            Rect::new(0, 0, 50, 50),
+            Rect::new(50, 0, 50, 50),
+            Rect::new(0, 50, 50, 50),
+            Rect::new(50, 50, 50, 50),
+        ]);
+
+        /* a single cell fills the scope */
+        let mut cells = vec![Cell::Hide];
+        Grid::new().arrange(&scope, &mut cells);
+        assert_eq!(rects(&cells), vec![scope]);
+    }
+
+    #[test]
+    fn test_dwindle() {
+        let scope = Rect::new(0, 0, 100, 100);
+        let mut cells: Vec<Cell> = (0..3).map(|_| Cell::Hide).collect();
+
+        Dwindle::new().arrange(&scope, &mut cells);
+
+        assert_eq!(rects(&cells), vec![
+            Rect::new(0, 0, 50, 100),
+            Rect::new(50, 0, 50, 50),
+            Rect::new(50, 50, 50, 50),
+        ]);
+    }
+
+    #[test]
+    fn test_spiral() {
+        let scope = Rect::new(0, 0, 100, 100);
+        let mut cells: Vec<Cell> = (0..5).map(|_| Cell::Hide).collect();
+
+        Spiral::new().arrange(&scope, &mut cells);
+
+        assert_eq!(rects(&cells), vec![
+            Rect::new(0, 0, 50, 100),
+            Rect::new(50, 0, 50, 50),
+            Rect::new(75, 50, 25, 50),
+            Rect::new(50, 75, 25, 25),
+            Rect::new(50, 50, 25, 25),
+        ]);
+    }
+
+    #[test]
+    fn test_fibonacci_focus() {
+        let scope = Rect::new(0, 0, 100, 100);
+        let mut cells = vec![Cell::Hide, Cell::Focus(scope)];
+        let mut layout = Spiral::new();
+
+        assert!(layout.handle(LayoutMessage::SetFactor(0.7)));
+        layout.arrange(&scope, &mut cells);
+
+        match cells[1] {
+            Cell::Focus(r) => assert_eq!(r, Rect::new(70, 0, 30, 100)),
+            _ => panic!("focus was lost"),
+        }
+    }
+
+    #[test]
+    fn test_left_master_arrange() {
+        let scope = Rect::new(0, 0, 100, 100);
+        let mut layout = LeftMaster::new();
+
+        layout.handle(LayoutMessage::IncMaster(-1));
+        layout.handle(LayoutMessage::SetFactor(0.6));
+
+        let mut cells: Vec<Cell> = (0..3).map(|_| Cell::Hide).collect();
+        layout.arrange(&scope, &mut cells);
+
+        assert_eq!(rects(&cells), vec![
+            Rect::new(0, 0, 60, 100),
+            Rect::new(60, 0, 40, 50),
+            Rect::new(60, 50, 40, 50),
+        ]);
+
+        /* a second column splits the stack side by side */
+        layout.handle(LayoutMessage::IncColumns(1));
+        layout.arrange(&scope, &mut cells);
+
+        assert_eq!(rects(&cells), vec![
+            Rect::new(0, 0, 60, 100),
+            Rect::new(60, 0, 20, 100),
+            Rect::new(80, 0, 20, 100),
+        ]);
+    }
+
+    #[test]
+    fn test_tabbed() {
+        let scope = Rect::new(0, 0, 100, 100);
+        let mut cells = vec![Cell::Hide, Cell::Focus(scope), Cell::Hide];
+        let mut layout = Tabbed::with_height(10);
+
+        layout.titles(vec!["a".into(), "b".into(), "c".into()]);
+        layout.arrange(&scope, &mut cells);
+
+        assert_eq!(rects(&cells), vec![Rect::new(0, 10, 100, 90)]);
+        assert!(matches!(cells[1], Cell::Focus(_)));
+
+        let decoration = layout.decoration().unwrap();
+        assert_eq!(decoration.rect, Rect::new(0, 0, 100, 10));
+
+        let active: Vec<_> = decoration.tabs.iter().map(|tab| tab.active).collect();
+        assert_eq!(active, vec![false, true, false]);
+        assert_eq!(decoration.tabs[2].title, "c");
+
+        let area = Rect::new(0, 0, 90, 10);
+        let tabs: Vec<_> = decoration.split(&area).collect();
+        assert_eq!(tabs[1], Rect::new(30, 0, 30, 10));
+
+        /* the selected child stays shown once focus leaves */
+        let mut cells: Vec<Cell> = (0..3).map(|_| Cell::Hide).collect();
+        layout.arrange(&scope, &mut cells);
+        assert!(matches!(cells[1], Cell::Show(_)));
+    }
+
+    #[test]
+    fn test_stacked() {
+        let scope = Rect::new(0, 0, 100, 100);
+        let mut cells: Vec<Cell> = (0..3).map(|_| Cell::Hide).collect();
+        let mut layout = Stacked::with_height(10);
+
+        layout.arrange(&scope, &mut cells);
+
+        assert_eq!(rects(&cells), vec![Rect::new(0, 30, 100, 70)]);
+
+        let decoration = layout.decoration().unwrap();
+        assert_eq!(decoration.rect, Rect::new(0, 0, 100, 30));
+        assert!(decoration.tabs[0].active);
+
+        let mut cells = vec![];
+        layout.arrange(&scope, &mut cells);
+        assert!(layout.decoration().is_none());
+    }
+
+    #[test]
+    fn test_centered_master() {
+        let scope = Rect::new(0, 0, 100, 100);
+        let mut layout = CenteredMaster::new();
+
+        let mut cells: Vec<Cell> = (0..4).map(|_| Cell::Hide).collect();
+        layout.arrange(&scope, &mut cells);
+
+        assert_eq!(rects(&cells), vec![
+            Rect::new(25, 0, 50, 100),
+            Rect::new(75, 0, 25, 50),
+            Rect::new(0, 0, 25, 100),
+            Rect::new(75, 50, 25, 50),
+        ]);
+
+        /* a single stack client sits to the right of the master */
+        let mut cells: Vec<Cell> = (0..2).map(|_| Cell::Hide).collect();
+        layout.arrange(&scope, &mut cells);
+
+        assert_eq!(rects(&cells), vec![
+            Rect::new(0, 0, 50, 100),
+            Rect::new(50, 0, 50, 100),
+        ]);
+
+        /* fewer clients than masters share the whole area */
+        assert!(layout.handle(LayoutMessage::IncMaster(2)));
+        layout.arrange(&scope, &mut cells);
+
+        assert_eq!(rects(&cells), vec![
+            Rect::new(0, 0, 100, 50),
+            Rect::new(0, 50, 100, 50),
+        ]);
+    }
+
+    #[test]
+    fn test_bottom_stack() {
+        let scope = Rect::new(0, 0, 100, 100);
+        let mut layout = BottomStack::new();
+
+        assert!(layout.handle(LayoutMessage::SetFactor(0.6)));
+        assert!(!layout.handle(LayoutMessage::IncColumns(1)));
+
+        let mut cells: Vec<Cell> = (0..3).map(|_| Cell::Hide).collect();
+        layout.arrange(&scope, &mut cells);
+
+        assert_eq!(rects(&cells), vec![
+            Rect::new(0, 0, 100, 60),
+            Rect::new(0, 60, 50, 40),
+            Rect::new(50, 60, 50, 40),
+        ]);
+
+        let mut cells = vec![Cell::Focus(scope)];
+        layout.arrange(&scope, &mut cells);
+
+        match cells[0] {
+            Cell::Focus(r) => assert_eq!(r, scope),
+            _ => panic!("focus was lost"),
+        }
+    }
+
+    #[test]
+    fn test_binary_split() {
+        let scope = Rect::new(0, 0, 100, 100);
+        let mut cells: Vec<Cell> = (0..2).map(|_| Cell::Hide).collect();
+
+        let mut layout = BinarySplit::vertical();
+        assert!(layout.handle(LayoutMessage::SetFactor(0.25)));
+        layout.arrange(&scope, &mut cells);
+
+        assert_eq!(rects(&cells), vec![
+            Rect::new(0, 0, 100, 25),
+            Rect::new(0, 25, 100, 75),
+        ]);
+
+        let mut cells: Vec<Cell> = (0..3).map(|_| Cell::Hide).collect();
+        BinarySplit::horizontal().arrange(&scope, &mut cells);
+
+        assert_eq!(rects(&cells), vec![
+            Rect::new(0, 0, 34, 100),
+            Rect::new(34, 0, 34, 100),
+            Rect::new(68, 0, 32, 100),
+        ]);
+    }
+
+    #[test]
+    fn test_scrolling() {
+        let scope = Rect::new(0, 0, 100, 100);
+        let mut layout = Scrolling::new();
+
+        let mut cells: Vec<Cell> = (0..3).map(|_| Cell::Hide).collect();
+        cells[0] = Cell::Focus(scope);
+        layout.arrange(&scope, &mut cells);
+
+        assert_eq!(rects(&cells), vec![
+            Rect::new(0, 0, 50, 100),
+            Rect::new(50, 0, 50, 100),
+        ]);
+        assert!(matches!(cells[2], Cell::Hide));
+
+        /* focusing the last column scrolls by one column */
+        let mut cells: Vec<Cell> = (0..3).map(|_| Cell::Hide).collect();
+        cells[2] = Cell::Focus(scope);
+        layout.arrange(&scope, &mut cells);
+
+        assert_eq!(layout.offset(), 50);
+        assert!(matches!(cells[0], Cell::Hide));
+        assert_eq!(rects(&cells), vec![
+            Rect::new(0, 0, 50, 100),
+            Rect::new(50, 0, 50, 100),
+        ]);
+
+        /* focus stays in view when the focused column grows */
+        assert!(layout.handle(LayoutMessage::SetFactor(0.75)));
+        layout.arrange(&scope, &mut cells);
+        assert_eq!(rects(&cells), vec![Rect::new(25, 0, 75, 100)]);
+    }
+
+    #[test]
+    fn test_scrolling_columns() {
+        let scope = Rect::new(0, 0, 100, 100);
+        let mut layout = Scrolling::new();
+
+        let mut cells: Vec<Cell> = (0..3).map(|_| Cell::Hide).collect();
+        cells[0] = Cell::Focus(scope);
+        layout.arrange(&scope, &mut cells);
+
+        /* pull the second client under the first */
+        assert!(layout.handle(LayoutMessage::IncMaster(1)));
+        layout.arrange(&scope, &mut cells);
+
+        assert_eq!(rects(&cells), vec![
+            Rect::new(0, 0, 50, 50),
+            Rect::new(0, 50, 50, 50),
+            Rect::new(50, 0, 50, 100),
+        ]);
+
+        assert!(layout.handle(LayoutMessage::IncMaster(-1)));
+        assert!(!layout.handle(LayoutMessage::IncMaster(-1)));
+
+        /* without ids, removed cells are taken from the end */
+        let mut cells = vec![Cell::Hide];
+        layout.arrange(&scope, &mut cells);
+        assert_eq!(rects(&cells), vec![Rect::new(0, 0, 50, 100)]);
+    }
+
+    #[test]
+    fn test_scrolling_remove() {
+        let scope = Rect::new(0, 0, 100, 100);
+        let mut layout = Scrolling::new();
+
+        let mut cells: Vec<Cell> = (0..3).map(|_| Cell::Hide).collect();
+        cells[0] = Cell::Focus(scope);
+        layout.children(&[10, 20, 30]);
+        layout.arrange(&scope, &mut cells);
+
+        /* stack the first two clients, leaving the third on its own */
+        assert!(layout.handle(LayoutMessage::IncMaster(1)));
+
+        /* the middle client leaves, the last keeps its own column */
+        let mut cells = vec![Cell::Focus(scope), Cell::Hide];
+        layout.children(&[10, 30]);
+        layout.arrange(&scope, &mut cells);
+
+        assert_eq!(rects(&cells), vec![
+            Rect::new(0, 0, 50, 100),
+            Rect::new(50, 0, 50, 100),
+        ]);
+    }
 }
@@ -1,33 +1,67 @@
 #![allow(dead_code)]
 use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::path::PathBuf;
 
+mod atom;
 mod client;
 mod display;
 mod error;
+mod history;
 mod keyboard;
 mod keysym;
 mod layout;
 mod painter;
 mod rect;
+mod restart;
+mod status;
 mod tag;
+mod tray;
 mod tree;
 mod window;
 mod wm;
 mod slab;
 
-use crate::display::MonitorId;
-use crate::tag::{Tag, TagSet, TagSetId, Tags};
-use crate::rect::{Rect, Cut};
+use crate::display::{Insert, MonitorId};
+use crate::restart::{ClientState, State};
+use crate::tag::{Tag, TagSelection, TagSet, TagSetId, Tags};
+use crate::window::ClientId;
+use crate::rect::{Cut, Direction, Point, Rect};
 use crate::slab::{SlabMap, AsIndex};
 
+use clap::Parser;
 use xcb::x;
 
+#[derive(Parser)]
+struct Args {
+    /// Restore the state saved by a previous instance before restarting
+    #[clap(long)]
+    restore: Option<PathBuf>,
+}
+
 #[derive(Copy, Clone)]
 enum Event {
     Exit,
+    Restart,
     Kill,
     FocusNext,
     FocusPrevious,
+    FocusDirection(Direction),
+    SwapDirection(Direction),
+    Gaps(i16),
+    Layout(layout::LayoutMessage),
+    NextLayout,
+    SplitMode,
+    SwapNext,
+    SwapPrevious,
+    Zoom,
+    Rotate(bool),
+    MoveInto(bool),
+    SendMonitor(bool),
+    FocusRecent(usize),
+    CycleRecent(bool),
+    CommitRecent,
+    Preselect(Direction),
     MonitorSet(MonitorId, TagSetId, Tag),
     MonitorUpdate(MonitorId, TagSetId, Tag),
     ClientSet(TagSetId, Tag),
@@ -41,30 +75,40 @@ struct Colorscheme {
     text: painter::Color,
     occupied: painter::Color,
     unoccupied: painter::Color,
+    urgent: painter::Color,
 }
 
 impl Colorscheme {
     fn new(conn: &mut wm::Connection<Event>, wm: &mut wm::WindowManager<Event>) -> Result<Colorscheme, error::Error> {
         let painter = wm.get_painter_mut();
 
-        Ok(Colorscheme {
-            focus: painter.color(conn, 55, 146, 237)?,
-            unfocus: painter.color(conn, 22, 22, 22)?,
-            occupied: painter.color(conn, 55, 237, 237)?,
-            unoccupied: painter.color(conn, 237, 146, 237)?,
+        let colorscheme = Colorscheme {
+            focus: painter.parse(conn, "#3792ed")?,
+            unfocus: painter.parse(conn, "#161616")?,
+            occupied: painter.parse(conn, "#37eded")?,
+            unoccupied: painter.parse(conn, "#ed92ed")?,
+            urgent: painter.parse(conn, "#ed3737")?,
             text: painter.white(),
-        })
+        };
+
+        /* the scheme is used for as long as we run */
+        painter.pin();
+
+        Ok(colorscheme)
     }
 }
 
 struct MonitorInfo {
     bar: Rect,
     window: Rect,
+    surface: Option<painter::Surface>,
     tagsets: Vec<TagSetId>,
 }
 
 impl MonitorInfo {
     const BAR_HEIGHT: u16 = 15;
+    const TAG_WIDTH: u16 = 10;
+    const PADDING: u16 = 4;
 
     fn connect(rect: &Rect) -> Self {
         let (bar, window) = rect.cut(Cut::Horizontal(MonitorInfo::BAR_HEIGHT));
@@ -72,6 +116,7 @@ impl MonitorInfo {
         MonitorInfo {
             bar: bar,
             window: window,
+            surface: None,
             tagsets: vec![],
         }
     }
@@ -87,28 +132,152 @@ impl MonitorInfo {
         self.bar = bar;
         self.window = window;
     }
+
+    /// Get the width and height of a tag square in the bar, along with the
+    /// width of the whole tag grid. Each tagset is drawn as one row.
+    fn grid(&self, tags: &Tags) -> (u16, u16, u16) {
+        let max = tags.iter()
+            .map(|(_, tagset)| tagset.len())
+            .max()
+            .unwrap_or(0);
+
+        let count = tags.len().max(1);
+
+        let width = MonitorInfo::TAG_WIDTH;
+        let height = self.bar.h / count as u16;
+        let length = (width * max as u16).min(self.bar.w);
+
+        (width, height, length)
+    }
+
+    /// Find the tag under a point in the bar, relative to the bar
+    fn hit(&self, tags: &Tags, point: Point) -> Option<(TagSetId, usize)> {
+        let (width, height, length) = self.grid(tags);
+
+        if point.x < 0 || point.y < 0 || point.x >= length as i16 || height == 0 {
+            return None;
+        }
+
+        let row = point.y as usize / height as usize;
+        let column = point.x as usize / width as usize;
+
+        let (tid, tagset) = tags.iter().nth(row)?;
+
+        if column < tagset.len() {
+            Some((tid, column))
+        } else {
+            None
+        }
+    }
+
+    /// Translate a click on the bar of a monitor into an event. Clicking a
+    /// tag views it, or toggles it with the right button, and holding the
+    /// modifier applies the tag to the focused client instead, which is
+    /// only done from the focused monitor's bar. Scrolling cycles through
+    /// the tags of a row.
+    fn click(
+        &self,
+        tags: &Tags,
+        id: MonitorId,
+        focused: bool,
+        point: Point,
+        button: x::Button,
+        modifiers: keyboard::Modifier,
+    ) -> Option<Event> {
+        let (tid, index) = self.hit(tags, point)?;
+        let client = modifiers.contains(keyboard::Modifier::MOD4);
+
+        match (button, client) {
+            (1, false) => Some(Event::MonitorSet(id, tid, Tag::On(index))),
+            (3, false) => Some(Event::MonitorUpdate(id, tid, Tag::Toggle(index))),
+            (1, true) if focused => Some(Event::ClientSet(tid, Tag::On(index))),
+            (3, true) if focused => Some(Event::ClientUpdate(tid, Tag::Toggle(index))),
+            (4, _) | (5, _) => {
+                let tagset = &tags[tid];
+                let count = tagset.len();
+                let current = tagset.tags()
+                    .position(|(_, enabled)| enabled)
+                    .unwrap_or(0);
+
+                let next = if button == 4 {
+                    (current + count - 1) % count
+                } else {
+                    (current + 1) % count
+                };
+
+                Some(Event::MonitorSet(id, tid, Tag::On(next)))
+            }
+            _ => None,
+        }
+    }
 }
 
 struct Manager {
     conn: wm::Connection<Event>,
     wm: wm::WindowManager<Event>,
     colorscheme: Colorscheme,
+    font: painter::Font,
+    status: String,
     tags: Tags,
+    layouts: layout::Cycle,
     monitors: HashMap<MonitorId, MonitorInfo>,
+    /* state from a previous instance, and windows which existed at startup */
+    restore: Option<State>,
+    windows: Vec<(x::Window, bool)>,
+    /* position in the focus history while cycling through it */
+    recent: Option<usize>,
 }
 
 impl Manager {
-    fn new(name: Option<&str>) -> Result<Self, error::Error> {
+    fn new(name: Option<&str>, restore: Option<State>) -> Result<Self, error::Error> {
         let mut conn = wm::Connection::connect(name)?;
         let mut wm = wm::WindowManager::new(&mut conn)?;
         let colorscheme = Colorscheme::new(&mut conn, &mut wm)?;
+        let font = wm.get_painter_mut().font(&mut conn, "fixed")?;
+
+        wm.set_borders(client::Borders {
+            width: 2,
+            focus: colorscheme.focus.pixel(),
+            unfocus: colorscheme.unfocus.pixel(),
+            urgent: colorscheme.urgent.pixel(),
+            floating: colorscheme.occupied.pixel(),
+            smart: true,
+        });
+
+        wm.set_gaps(layout::Gaps {
+            inner: 6,
+            outer: 6,
+            smart: true,
+        });
+
+        let mut layouts = layout::Cycle::new();
+        layouts.push(|| Box::new(layout::LeftMaster::new()));
+        layouts.push(|| Box::new(layout::CenteredMaster::new()));
+        layouts.push(|| Box::new(layout::BottomStack::new()));
+        layouts.push(|| Box::new(layout::Scrolling::new()));
+        layouts.push(|| Box::new(layout::Monacle::new()));
+        layouts.push(|| Box::new(layout::Grid::new()));
+        layouts.push(|| Box::new(layout::Spiral::new()));
+        layouts.push(|| Box::new(layout::Dwindle::new()));
+        layouts.push(|| Box::new(layout::Tabbed::new()));
+        layouts.push(|| Box::new(layout::Stacked::new()));
+
+        if let Some(tray) = wm.tray_mut() {
+            tray.set_background(&mut conn, colorscheme.unfocus.pixel())?;
+        }
 
         Ok(Manager {
             conn: conn,
             wm: wm,
             colorscheme: colorscheme,
+            font: font,
+            status: String::new(),
             tags: Tags::new(),
+            layouts: layouts,
             monitors: HashMap::new(),
+            restore: restore,
+            windows: vec![],
+            recent: None,
         })
     }
 
@@ -163,42 +332,443 @@ impl Manager {
     }
 
     fn arrange(&mut self) -> Result<(), error::Error> {
-        let conn = &mut self.conn;
+        if let Some(id) = self.wm.get_monitor() {
+            self.arrange_monitor(id)?;
+        }
+
+        Ok(())
+    }
+
+    fn arrange_monitor(&mut self, id: MonitorId) -> Result<(), error::Error> {
+        let info = &self.monitors[&id];
+        let selection = self.tags.select(&info.tagsets);
+
+        let mon = &mut self.wm[id];
+        mon.arrange(&mut self.conn, &selection)?;
+
+        /* arranging may have moved focus onto a client it showed */
+        self.wm.remember();
+
+        self.decorate(id)
+    }
+
+    /// Draw the titles of tabbed and stacked layouts on a monitor
+    fn decorate(&mut self, id: MonitorId) -> Result<(), error::Error> {
+        let frames: Vec<_> = self.wm[id].decorations()
+            .map(|(window, rect, decoration)| (window, *rect, decoration.clone()))
+            .collect();
+
+        let painter = self.wm.get_painter_mut();
+
+        for (window, rect, decoration) in frames.iter() {
+            /* draw relative to the frame window */
+            let area = Rect::new(0, 0, rect.w, rect.h);
+
+            painter.target(x::Drawable::Window(*window));
+
+            for (tab, rect) in decoration.tabs.iter().zip(decoration.split(&area)) {
+                let background = if tab.active {
+                    self.colorscheme.focus
+                } else {
+                    self.colorscheme.unfocus
+                };
+
+                painter.brush(&mut self.conn, background, background)?;
+                painter.rect(&mut self.conn, &rect)?;
+
+                let text = rect.shrink(0, 0, 0, MonitorInfo::PADDING);
+
+                painter.brush(&mut self.conn, self.colorscheme.text, background)?;
+                painter.text(&mut self.conn, &self.font, &text, &tab.title)?;
+            }
+        }
+
+        self.conn.flush()?;
+
+        Ok(())
+    }
+
+    /// Focus the next or previous client visible on the focused monitor
+    fn cycle(&mut self, forward: bool) -> Result<(), error::Error> {
+        let id = match self.wm.get_monitor() {
+            Some(id) => id,
+            None => return Ok(()),
+        };
+
+        let info = &self.monitors[&id];
+        let selection = self.tags.select(&info.tagsets);
+
+        let next = if forward {
+            self.wm.next_client(&selection)
+        } else {
+            self.wm.previous_client(&selection)
+        };
+
+        if let Some((mid, cid)) = next {
+            /* the layout may need to show the client, or scroll to it,
+             * before it can take input focus */
+            self.wm[mid].focus = Some(cid);
+            self.arrange_monitor(mid)?;
+            self.wm.set_focus(&mut self.conn, mid, cid)?;
+        }
+
+        self.drawbar(id)
+    }
+
+    /// Focus a client on any monitor, first switching the tags viewed
+    /// there to one of its tags if it is hidden
+    fn reveal(&mut self, mid: MonitorId, cid: ClientId) -> Result<(), error::Error> {
+        let previous = self.wm.get_monitor();
+        let info = &self.monitors[&mid];
+
+        for tid in info.tagsets.iter() {
+            let mask = match self.wm[mid][cid].mask().get(tid.as_index()) {
+                Some(mask) => mask.clone(),
+                None => tag::TagMask::new(),
+            };
+
+            let tagset = &mut self.tags[*tid];
+
+            if tagset.mask().visible(&mask) {
+                continue;
+            }
+
+            if let Some(index) = mask.iter().position(|bit| *bit) {
+                tagset.mask_mut().clear();
+                tagset.mask_mut().set(Tag::On(index));
+            }
+        }
+
+        /* the layout has to show the client before it can take input
+         * focus */
+        self.wm[mid].focus = Some(cid);
+        self.arrange_monitor(mid)?;
+        self.wm.set_focus(&mut self.conn, mid, cid)?;
+
+        self.drawbar(mid)?;
+
+        if let Some(previous) = previous.filter(|id| *id != mid) {
+            self.drawbar(previous)?;
+        }
+
+        Ok(())
+    }
+
+    /// Step through the focus history while the modifier is held, showing
+    /// each client in turn. The keyboard is grabbed on the first step so
+    /// that releasing either Alt key reaches us and commits the choice.
+    fn cycle_recent(&mut self, forward: bool) -> Result<(), error::Error> {
+        let count = self.wm.history().len();
+
+        if count < 2 {
+            return Ok(());
+        }
+
+        let position = match self.recent {
+            Some(position) if forward => (position + 1) % count,
+            Some(position) => (position + count - 1) % count,
+            None => {
+                if !self.wm.grab_keyboard(&mut self.conn, keyboard::Modifier::MOD1)? {
+                    /* without the grab we would never see the release,
+                     * and after a quick tap it has already happened, so
+                     * just switch to the previous client */
+                    return self.focus_recent(1);
+                }
+
+                self.wm.history_mut().freeze();
+
+                if forward { 1 } else { count - 1 }
+            }
+        };
+
+        self.recent = Some(position);
+
+        match self.wm.recent(position) {
+            Some((mid, cid)) => self.reveal(mid, cid),
+            None => Ok(()),
+        }
+    }
+
+    /// Finish cycling through the focus history, making the client shown
+    /// the most recent
+    fn commit_recent(&mut self) -> Result<(), error::Error> {
+        if self.recent.take().is_some() {
+            self.wm.ungrab_keyboard(&mut self.conn)?;
+            self.wm.history_mut().thaw();
+            self.wm.remember();
+        }
+
+        Ok(())
+    }
+
+    /// Take the keyboard back if the grab for cycling through the focus
+    /// history was lost, or commit if Alt was let go while it was gone
+    fn regrab_recent(&mut self) -> Result<(), error::Error> {
+        if self.recent.is_none() {
+            return Ok(());
+        }
+
+        if !self.wm.grab_keyboard(&mut self.conn, keyboard::Modifier::MOD1)? {
+            self.commit_recent()?;
+        }
+
+        Ok(())
+    }
+
+    /// Focus the nth most recently focused client
+    fn focus_recent(&mut self, n: usize) -> Result<(), error::Error> {
+        match self.wm.recent(n) {
+            Some((mid, cid)) => self.reveal(mid, cid),
+            None => Ok(()),
+        }
+    }
 
+    /// Move clients around on the focused monitor, rearranging it if
+    /// anything moved. Focus stays on the focused client, which keeps its
+    /// id wherever it goes.
+    fn reorder(&mut self, f: impl FnOnce(&mut display::Monitor, &TagSelection) -> bool) -> Result<(), error::Error> {
         if let Some(id) = self.wm.get_monitor() {
             let info = &self.monitors[&id];
             let selection = self.tags.select(&info.tagsets);
 
-            let mon = &mut self.wm[id];
-            mon.arrange(conn, &selection)?;
+            if f(&mut self.wm[id], &selection) {
+                self.arrange_monitor(id)?;
+            }
         }
 
         Ok(())
     }
 
-    fn drawbar(&mut self, id: MonitorId) -> Result<(), error::Error> {
-        let info = &self.monitors[&id];
-        let bar = info.bar;
+    /// Send the focused client to the next or previous monitor, where it
+    /// takes on the tags being viewed
+    fn send(&mut self, forward: bool) -> Result<(), error::Error> {
+        let (from, cid) = match self.wm.get_focus() {
+            Some(focus) => focus,
+            None => return Ok(()),
+        };
+
+        let ids: Vec<_> = self.wm.display().iter()
+            .map(|(id, _)| id)
+            .collect();
+
+        let count = ids.len();
+        let position = ids.iter().position(|id| *id == from).unwrap_or(0);
+
+        let to = if forward {
+            ids[(position + 1) % count]
+        } else {
+            ids[(position + count - 1) % count]
+        };
+
+        if to == from {
+            return Ok(());
+        }
+
+        let cid = self.wm.display_mut().move_client(from, cid, to);
+        self.assign(to, cid);
+
+        self.arrange_monitor(from)?;
+        self.arrange_monitor(to)?;
+
+        self.drawbar(from)?;
+        self.drawbar(to)?;
+
+        Ok(())
+    }
+
+    /// Drop tagsets which are no longer shown anywhere, along with the
+    /// masks clients hold for them and the layouts saved for their views
+    fn release(&mut self, tagsets: &[TagSetId]) {
+        let clients: Vec<_> = self.wm.display().iter()
+            .flat_map(|(mid, mon)| mon.walk().map(move |(cid, _)| (mid, cid)))
+            .collect();
+
+        let monitors: Vec<_> = self.wm.display().iter()
+            .map(|(mid, _)| mid)
+            .collect();
+
+        for tid in tagsets.iter() {
+            self.tags.remove(*tid);
+
+            for (mid, cid) in clients.iter() {
+                self.wm[*mid][*cid].mask_mut().remove(tid.as_index());
+            }
+
+            for mid in monitors.iter() {
+                self.wm[*mid].forget(*tid);
+            }
+        }
+    }
+
+    /// Assign the currently selected masks to a client
+    fn assign(&mut self, mid: MonitorId, cid: ClientId) {
+        let client = &mut self.wm[mid][cid];
+
+        for (id, tagset) in self.tags.iter() {
+            client.insert_mask(id, tagset.mask().clone());
+        }
+    }
+
+    /// Manage the windows which existed before we started. Windows saved by
+    /// a previous instance go back to their monitor in their previous order,
+    /// anything else which is mapped goes to the focused monitor.
+    fn adopt(&mut self, id: MonitorId) -> Result<(), error::Error> {
+        let name = String::from(self.wm[id].name());
+        let fallback = self.wm.get_monitor() == Some(id);
+
+        if let Some(state) = &self.restore {
+            let info = &self.monitors[&id];
+
+            for (tid, mask) in state.tagsets.iter() {
+                if info.tagsets.contains(tid) {
+                    *self.tags[*tid].mask_mut() = mask.clone();
+                }
+            }
+
+            for client in state.clients.iter() {
+                let missing = !self.wm.display().iter()
+                    .any(|(_, mon)| mon.name() == client.monitor);
+
+                if client.monitor != name && !(fallback && missing) {
+                    continue;
+                }
 
-        let mut max = 0;
-        for (_, tagset) in self.tags.iter() {
-            if tagset.len() > max {
-                max = tagset.len();
+                let at = self.windows.iter()
+                    .position(|(window, _)| *window == client.window);
+
+                if let Some(at) = at {
+                    let (window, mapped) = self.windows.remove(at);
+                    let cid = self.wm.adopt(&mut self.conn, id, window, mapped)?;
+
+                    for (tid, mask) in client.masks.iter() {
+                        self.wm[id][cid].insert_mask(*tid, mask.clone());
+                    }
+                }
+            }
+        }
+
+        if fallback {
+            let windows = std::mem::take(&mut self.windows);
+
+            for (window, mapped) in windows {
+                /* leave windows which are waiting on another monitor */
+                let waiting = self.restore.as_ref()
+                    .and_then(|state| state.client(window))
+                    .is_some();
+
+                if waiting {
+                    self.windows.push((window, mapped));
+                } else if mapped {
+                    let cid = self.wm.adopt(&mut self.conn, id, window, mapped)?;
+                    self.assign(id, cid);
+                }
             }
         }
 
-        let length: u16 = (bar.w as f32 * 0.80) as u16;
+        self.arrange_monitor(id)?;
+
+        let focus = self.restore.as_ref()
+            .and_then(|state| state.focus(&name))
+            .and_then(|window| self.wm[id].find(window));
+
+        if let Some(cid) = focus {
+            if self.wm[id][cid].visible() {
+                self.wm.set_focus(&mut self.conn, id, cid)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Save our state and replace this process with a new instance. This
+    /// only returns if the restart fails.
+    fn restart(&mut self) -> Result<(), error::Error> {
+        let mut state = State::new();
+
+        for (id, tagset) in self.tags.iter() {
+            state.tagsets.push((id, tagset.mask().clone()));
+        }
+
+        for (_, mon) in self.wm.display().iter() {
+            let name = String::from(mon.name());
+
+            if let Some(focus) = mon.focus {
+                state.focus.push((name.clone(), mon[focus].window()));
+            }
+
+            for (_, client) in mon.walk() {
+                let masks = client.mask()
+                    .iter()
+                    .map(|(id, mask)| (TagSetId::from(id), mask.clone()))
+                    .collect();
+
+                state.clients.push(ClientState {
+                    window: client.window(),
+                    monitor: name.clone(),
+                    masks: masks,
+                });
+            }
+        }
+
+        let path = std::env::temp_dir()
+            .join(format!("wm-{}.state", std::process::id()));
+
+        state.save(&path)?;
+
+        if let Some(tray) = self.wm.tray() {
+            tray.release(&mut self.conn)?;
+        }
+
+        Err(self.wm.restart(&[OsStr::new("--restore"), path.as_os_str()]))
+    }
+
+    /// Move the tray to the primary monitor's bar
+    fn place_tray(&mut self) -> Result<(), error::Error> {
+        let bar = self.wm.display()
+            .get_primary()
+            .and_then(|id| self.monitors.get(&id))
+            .map(|info| info.bar);
+
+        match (bar, self.wm.tray_mut()) {
+            (Some(bar), Some(tray)) => tray.place(&mut self.conn, &bar),
+            _ => Ok(()),
+        }
+    }
+
+    /// Redraw the bar of every monitor
+    fn drawbars(&mut self) -> Result<(), error::Error> {
+        let ids: Vec<_> = self.monitors.keys().copied().collect();
+
+        for id in ids {
+            self.drawbar(id)?;
+        }
+
+        Ok(())
+    }
+
+    fn drawbar(&mut self, id: MonitorId) -> Result<(), error::Error> {
+        let info = &self.monitors[&id];
+
+        let surface = match info.surface {
+            Some(ref surface) => surface,
+            None => return Ok(()),
+        };
+
+        /* draw relative to the bar window */
+        let bar = Rect::new(0, 0, info.bar.w, info.bar.h);
 
-        let count = self.tags.len();
-        let width =  length / max as u16;
-        let height = bar.h / count as u16;
+        let (width, height, length) = info.grid(&self.tags);
 
         let mut square = Rect::new(bar.x, bar.y, width, height);
         let mut occsq  = Rect::new(bar.x, bar.y, width / 2, height / 2);
 
         let mut occupied: SlabMap<tag::TagMask> = SlabMap::new();
 
-        for (_, client) in self.wm[id].clients() {
+        let monitor = &mut self.wm[id];
+        let symbol = String::from(monitor.symbol());
+        let title = String::from(monitor.title().unwrap_or(""));
+
+        for (_, client) in monitor.clients() {
             let mask = client.mask();
 
             for (id, mask) in mask.iter() {
@@ -211,7 +781,22 @@ impl Manager {
             }
         }
 
+        /* the tray covers the right edge of the primary monitor's bar */
+        let tray = if self.wm.display().get_primary() == Some(id) {
+            self.wm.tray().map_or(0, |tray| tray.width())
+        } else {
+            0
+        };
+
         let painter = self.wm.get_painter_mut();
+        painter.begin(surface);
+
+        painter.brush(
+            &mut self.conn,
+            self.colorscheme.unfocus,
+            self.colorscheme.unfocus)?;
+
+        painter.rect(&mut self.conn, &bar)?;
 
         for (id, tagset) in self.tags.iter() {
             let mask = &occupied.get(id.as_index());
@@ -256,6 +841,59 @@ impl Manager {
 
         }
 
+        /* layout symbol and title follow the tag grid, and are overdrawn
+         * by the status text if there is not enough room */
+        let mut x = bar.x + (length + MonitorInfo::PADDING) as i16;
+
+        for text in [&symbol, &title].iter().filter(|t| !t.is_empty()) {
+            let width = painter.measure(&mut self.conn, &self.font, text)?;
+            let rect = Rect::new(x, bar.y, width, bar.h);
+
+            painter.brush(&mut self.conn, self.colorscheme.text, self.colorscheme.unfocus)?;
+            painter.text(&mut self.conn, &self.font, &rect, text)?;
+
+            x += (width + MonitorInfo::PADDING) as i16;
+        }
+
+        /* status text, right aligned in the space after the tag grid */
+        let area = Rect::new(bar.x + length as i16, bar.y, bar.w.saturating_sub(length + tray), bar.h);
+        let segments = status::parse(&self.status);
+
+        let mut widths = Vec::with_capacity(segments.len());
+        for segment in segments.iter() {
+            widths.push(painter.measure(&mut self.conn, &self.font, segment.text)?);
+        }
+
+        let total: u16 = widths.iter().sum();
+        x = area.right() - total.min(area.w) as i16;
+
+        for (segment, width) in segments.iter().zip(widths) {
+            /* invalid colours in the status text fall back to the default */
+            let foreground = match segment.foreground {
+                Some(color) => painter.parse(&mut self.conn, color)
+                    .unwrap_or(self.colorscheme.text),
+                None => self.colorscheme.text,
+            };
+
+            let background = match segment.background {
+                Some(color) => painter.parse(&mut self.conn, color)
+                    .unwrap_or(self.colorscheme.unfocus),
+                None => self.colorscheme.unfocus,
+            };
+
+            let rect = Rect::new(x, area.y, width, area.h);
+
+            painter.brush(&mut self.conn, background, background)?;
+            painter.rect(&mut self.conn, &rect)?;
+
+            painter.brush(&mut self.conn, foreground, background)?;
+            painter.text(&mut self.conn, &self.font, &rect, segment.text)?;
+
+            x += width as i16;
+        }
+
+        painter.present(&mut self.conn, surface)?;
+
         Ok(())
     }
 
@@ -284,6 +922,14 @@ impl Manager {
             value: Event::Kill,
         })?;
 
+        self.wm.bind(&mut self.conn, &keyboard::Binding {
+            monitor: None,
+            mask: keyboard::Modifier::MOD4 | keyboard::Modifier::SHIFT,
+            keysym: keysym::r,
+            press: keyboard::Press::Press,
+            value: Event::Restart,
+        })?;
+
 
         self.wm.bind(&mut self.conn, &keyboard::Binding {
             monitor: None,
@@ -301,6 +947,158 @@ impl Manager {
             value: Event::FocusPrevious,
         })?;
 
+        self.wm.bind(&mut self.conn, &keyboard::Binding {
+            monitor: None,
+            mask: keyboard::Modifier::MOD4,
+            keysym: keysym::equal,
+            press: keyboard::Press::Press,
+            value: Event::Gaps(2),
+        })?;
+
+        self.wm.bind(&mut self.conn, &keyboard::Binding {
+            monitor: None,
+            mask: keyboard::Modifier::MOD4,
+            keysym: keysym::minus,
+            press: keyboard::Press::Press,
+            value: Event::Gaps(-2),
+        })?;
+
+        let messages = [
+            (keysym::h, layout::LayoutMessage::IncFactor(-0.05)),
+            (keysym::l, layout::LayoutMessage::IncFactor(0.05)),
+            (keysym::comma, layout::LayoutMessage::IncMaster(1)),
+            (keysym::period, layout::LayoutMessage::IncMaster(-1)),
+            (keysym::bracketleft, layout::LayoutMessage::IncColumns(-1)),
+            (keysym::bracketright, layout::LayoutMessage::IncColumns(1)),
+        ];
+
+        for (keysym, message) in messages.iter() {
+            self.wm.bind(&mut self.conn, &keyboard::Binding {
+                monitor: None,
+                mask: keyboard::Modifier::MOD4,
+                keysym: *keysym,
+                press: keyboard::Press::Press,
+                value: Event::Layout(*message),
+            })?;
+        }
+
+        self.wm.bind(&mut self.conn, &keyboard::Binding {
+            monitor: None,
+            mask: keyboard::Modifier::MOD4,
+            keysym: keysym::space,
+            press: keyboard::Press::Press,
+            value: Event::NextLayout,
+        })?;
+
+        let reorder = [
+            (keysym::j, Event::SwapNext),
+            (keysym::k, Event::SwapPrevious),
+            (keysym::Return, Event::Zoom),
+            (keysym::l, Event::Rotate(true)),
+            (keysym::h, Event::Rotate(false)),
+            (keysym::bracketright, Event::MoveInto(true)),
+            (keysym::bracketleft, Event::MoveInto(false)),
+            (keysym::period, Event::SendMonitor(true)),
+            (keysym::comma, Event::SendMonitor(false)),
+        ];
+
+        for (keysym, event) in reorder.iter() {
+            self.wm.bind(&mut self.conn, &keyboard::Binding {
+                monitor: None,
+                mask: keyboard::Modifier::MOD4 | keyboard::Modifier::SHIFT,
+                keysym: *keysym,
+                press: keyboard::Press::Press,
+                value: *event,
+            })?;
+        }
+
+        self.wm.bind(&mut self.conn, &keyboard::Binding {
+            monitor: None,
+            mask: keyboard::Modifier::MOD4,
+            keysym: keysym::Tab,
+            press: keyboard::Press::Press,
+            value: Event::FocusRecent(1),
+        })?;
+
+        let recent = [
+            (keyboard::Modifier::MOD1, Event::CycleRecent(true)),
+            (keyboard::Modifier::MOD1 | keyboard::Modifier::SHIFT, Event::CycleRecent(false)),
+        ];
+
+        for (mask, event) in recent.iter() {
+            self.wm.bind(&mut self.conn, &keyboard::Binding {
+                monitor: None,
+                mask: *mask,
+                keysym: keysym::Tab,
+                press: keyboard::Press::Press,
+                value: *event,
+            })?;
+
+            /* the state of a release still holds the key being released,
+             * and either Alt key may be the one held */
+            for keysym in [keysym::Alt_L, keysym::Alt_R].iter() {
+                self.wm.bind(&mut self.conn, &keyboard::Binding {
+                    monitor: None,
+                    mask: *mask,
+                    keysym: *keysym,
+                    press: keyboard::Press::Release,
+                    value: Event::CommitRecent,
+                })?;
+            }
+        }
+
+        self.wm.bind(&mut self.conn, &keyboard::Binding {
+            monitor: None,
+            mask: keyboard::Modifier::MOD4,
+            keysym: keysym::t,
+            press: keyboard::Press::Press,
+            value: Event::SplitMode,
+        })?;
+
+        let directions = [
+            (keysym::Left, Direction::Left),
+            (keysym::Down, Direction::Down),
+            (keysym::Up, Direction::Up),
+            (keysym::Right, Direction::Right),
+        ];
+
+        for (keysym, direction) in directions.iter() {
+            self.wm.bind(&mut self.conn, &keyboard::Binding {
+                monitor: None,
+                mask: keyboard::Modifier::MOD4,
+                keysym: *keysym,
+                press: keyboard::Press::Press,
+                value: Event::FocusDirection(*direction),
+            })?;
+
+            self.wm.bind(&mut self.conn, &keyboard::Binding {
+                monitor: None,
+                mask: keyboard::Modifier::MOD4 | keyboard::Modifier::SHIFT,
+                keysym: *keysym,
+                press: keyboard::Press::Press,
+                value: Event::SwapDirection(*direction),
+            })?;
+        }
+
+        let preselect = [
+            (keysym::h, Direction::Left),
+            (keysym::j, Direction::Down),
+            (keysym::k, Direction::Up),
+            (keysym::l, Direction::Right),
+        ];
+
+        for (keysym, direction) in preselect.iter() {
+            self.wm.bind(&mut self.conn, &keyboard::Binding {
+                monitor: None,
+                mask: keyboard::Modifier::MOD4 | keyboard::Modifier::CONTROL,
+                keysym: *keysym,
+                press: keyboard::Press::Press,
+                value: Event::Preselect(*direction),
+            })?;
+        }
+
+        self.windows = self.wm.scan(&mut self.conn)?;
+
         loop {
             match self.wm.next(&mut self.conn)? {
                 wm::Event::MonitorConnect(id) => {
@@ -319,8 +1117,15 @@ impl Manager {
                     info.tagset(lid);
 
                     monitor.set_rect(info.window);
+                    let window = monitor.set_bar(&mut self.conn, &info.bar)?;
+
+                    let painter = self.wm.get_painter_mut();
+                    let surface = painter.surface(&mut self.conn, window, info.bar.w, info.bar.h)?;
+                    info.surface = Some(surface);
+
                     self.monitors.insert(id, info);
 
+                    self.place_tray()?;
                     self.drawbar(id)?;
 
                     self.bindtag(keysym::a, id, uid, 0)?;
@@ -334,6 +1139,9 @@ impl Manager {
                     self.bindtag(keysym::c, id, lid, 2)?;
                     self.bindtag(keysym::v, id, lid, 3)?;
                     self.bindtag(keysym::b, id, lid, 4)?;
+
+                    self.adopt(id)?;
+                    self.drawbar(id)?;
                 },
                 wm::Event::MonitorResize(id) => {
                     let monitor = &mut self.wm[id];
@@ -342,20 +1150,93 @@ impl Manager {
 
                     info.resize(rect);
                     monitor.set_rect(info.window);
+                    monitor.set_bar(&mut self.conn, &info.bar)?;
+
+                    if let Some(ref mut surface) = info.surface {
+                        let painter = self.wm.get_painter_mut();
+                        painter.resize(&mut self.conn, surface, info.bar.w, info.bar.h)?;
+                    }
+
+                    self.place_tray()?;
                     self.drawbar(id)?;
 
                     self.arrange()?;
                 }
+                wm::Event::MonitorDisconnect(id, moved) => {
+                    if let Some(info) = self.monitors.remove(&id) {
+                        /* the bar window itself is destroyed along with the monitor */
+                        if let Some(surface) = info.surface {
+                            let painter = self.wm.get_painter_mut();
+                            painter.free(&mut self.conn, surface)?;
+                        }
+
+                        self.release(&info.tagsets);
+                    }
+
+                    self.wm.unbind(&mut self.conn, id)?;
+
+                    /* clients handed over take on the tags viewed where
+                     * they landed, or they could stay hidden there */
+                    for (mid, cid) in moved {
+                        self.assign(mid, cid);
+                    }
+
+                    self.place_tray()?;
+
+                    /* clients from the monitor are handed to another, so
+                     * everything needs to be rearranged */
+                    let ids: Vec<_> = self.monitors.keys().copied().collect();
+
+                    for id in ids {
+                        self.arrange_monitor(id)?;
+                        self.drawbar(id)?;
+                    }
+                }
+                wm::Event::BarExpose(id) => {
+                    self.drawbar(id)?;
+                }
+                wm::Event::DecorationExpose(id) => {
+                    self.decorate(id)?;
+                }
+                wm::Event::BarClick(id, point, button, modifiers) => {
+                    /* handled the same as the equivalent key binding */
+                    let focused = self.wm.get_monitor() == Some(id);
+                    let event = self.monitors.get(&id).and_then(|info| {
+                        info.click(&self.tags, id, focused, point, button, modifiers)
+                    });
+
+                    if let Some(event) = event {
+                        self.conn.push(wm::Event::UserEvent(event));
+                    }
+                }
+                wm::Event::ClientEnter(mid, _) => {
+                    self.drawbar(mid)?;
+                }
+                wm::Event::ClientName(mid, cid) => {
+                    /* names change often, so only redraw where they are
+                     * shown rather than arranging the monitor */
+                    if self.wm[mid].retitle(cid) {
+                        self.decorate(mid)?;
+                    }
+
+                    self.drawbar(mid)?;
+                }
+                wm::Event::StatusText(text) => {
+                    self.status = text;
+                    self.drawbars()?;
+                }
+                wm::Event::MonitorPrimary(_) | wm::Event::TrayUpdate => {
+                    /* the tray moved or changed size, so the status text
+                     * may need to move too */
+                    self.place_tray()?;
+                    self.drawbars()?;
+                }
                 wm::Event::UserEvent(Event::Spawn(args)) => {
                     self.wm.spawn(args);
                 }
                 wm::Event::ClientCreate(mid, cid) => {
-                    let client = &mut self.wm[mid][cid];
-
                     /* assign currently selected masks to new client */
-                    for (id, tagset) in self.tags.iter() {
-                        client.insert_mask(id, tagset.mask().clone());
-                    }
+                    self.assign(mid, cid);
 
                     self.arrange()?;
                     self.drawbar(mid)?;
@@ -418,25 +1299,125 @@ impl Manager {
                     }
                 }
                 wm::Event::UserEvent(Event::FocusNext) => {
-                    self.wm.next_client()
-                        .map(|(mid, cid)| {
-                            let display = self.wm.display_mut();
-                            display.set_focus(&mut self.conn, mid, cid)
-                        });
+                    self.cycle(true)?;
+                }
+                wm::Event::UserEvent(Event::FocusPrevious) => {
+                    self.cycle(false)?;
+                }
+                wm::Event::UserEvent(Event::FocusDirection(direction)) => {
+                    let previous = self.wm.get_monitor();
+
+                    if let Some((mid, _)) = self.wm.focus_direction(&mut self.conn, direction)? {
+                        self.arrange_monitor(mid)?;
+                        self.drawbar(mid)?;
+
+                        /* the bar of the monitor we left loses its title */
+                        if let Some(previous) = previous.filter(|id| *id != mid) {
+                            self.drawbar(previous)?;
+                        }
+                    }
+                }
+                wm::Event::UserEvent(Event::SwapDirection(direction)) => {
+                    let previous = self.wm.get_focus();
+
+                    match (previous, self.wm.swap_direction(direction)) {
+                        (Some((from, swapped)), Some((to, cid))) if from != to => {
+                            /* both clients take on the tags viewed where
+                             * they end up */
+                            self.assign(to, cid);
+                            self.assign(from, swapped);
+
+                            self.arrange_monitor(from)?;
+                            self.arrange_monitor(to)?;
+
+                            self.drawbar(from)?;
+                            self.drawbar(to)?;
+                        }
+                        (_, Some((mid, _))) => {
+                            self.arrange_monitor(mid)?;
+                        }
+                        _ => {}
+                    }
+                }
+                wm::Event::UserEvent(Event::Gaps(delta)) => {
+                    /* grow or shrink both gaps of the focused monitor */
+                    if let Some(id) = self.wm.get_monitor() {
+                        let mon = &mut self.wm[id];
+                        let mut gaps = *mon.gaps();
+
+                        gaps.inner = gaps.inner.saturating_add_signed(delta);
+                        gaps.outer = gaps.outer.saturating_add_signed(delta);
+                        mon.set_gaps(gaps);
 
+                        self.arrange_monitor(id)?;
+                    }
+                }
+                wm::Event::UserEvent(Event::Layout(msg)) => {
                     if let Some(id) = self.wm.get_monitor() {
-                        self.drawbar(id)?;
+                        if self.wm[id].message(msg) {
+                            self.arrange_monitor(id)?;
+                        }
                     }
                 }
-                wm::Event::UserEvent(Event::FocusPrevious) => {
-                    self.wm.previous_client()
-                        .map(|(mid, cid)| {
-                            let display = self.wm.display_mut();
-                            display.set_focus(&mut self.conn, mid, cid)
-                        });
+                wm::Event::UserEvent(Event::NextLayout) => {
+                    if let Some(id) = self.wm.get_monitor() {
+                        if self.wm[id].cycle(&self.layouts) {
+                            self.arrange_monitor(id)?;
+                            self.drawbar(id)?;
+                        }
+                    }
+                }
+                wm::Event::UserEvent(Event::SwapNext) => {
+                    self.reorder(|monitor, selection| monitor.swap_next(selection))?;
+                }
+                wm::Event::UserEvent(Event::SwapPrevious) => {
+                    self.reorder(|monitor, selection| monitor.swap_previous(selection))?;
+                }
+                wm::Event::UserEvent(Event::Zoom) => {
+                    self.reorder(|monitor, selection| monitor.zoom(selection))?;
+                }
+                wm::Event::UserEvent(Event::Rotate(forward)) => {
+                    self.reorder(|monitor, selection| monitor.rotate(forward, selection))?;
+                }
+                wm::Event::UserEvent(Event::MoveInto(forward)) => {
+                    self.reorder(|monitor, _| monitor.move_into_sibling(forward))?;
+                }
+                wm::Event::UserEvent(Event::SendMonitor(forward)) => {
+                    self.send(forward)?;
+                }
+                wm::Event::UserEvent(Event::FocusRecent(n)) => {
+                    self.focus_recent(n)?;
+                }
+                wm::Event::UserEvent(Event::CycleRecent(forward)) => {
+                    self.cycle_recent(forward)?;
+                }
+                wm::Event::UserEvent(Event::CommitRecent) => {
+                    self.commit_recent()?;
+                }
+                wm::Event::KeyboardUngrab => {
+                    self.regrab_recent()?;
+                }
+                wm::Event::UserEvent(Event::SplitMode) => {
+                    if let Some(id) = self.wm.get_monitor() {
+                        let monitor = &mut self.wm[id];
+
+                        let insert = match monitor.insert() {
+                            Insert::Append => Insert::Split,
+                            Insert::Split => Insert::Append,
+                        };
 
+                        monitor.set_insert(insert);
+                        monitor.set_preselect(None);
+                    }
+                }
+                wm::Event::UserEvent(Event::Preselect(direction)) => {
                     if let Some(id) = self.wm.get_monitor() {
-                        self.drawbar(id)?;
+                        self.wm[id].set_preselect(Some(direction));
+                    }
+                }
+                wm::Event::UserEvent(Event::Restart) => {
+                    if let Err(e) = self.restart() {
+                        eprintln!("failed to restart: {}", e);
                     }
                 }
                 wm::Event::UserEvent(Event::Exit) => {
@@ -449,9 +1430,82 @@ impl Manager {
 }
 
 fn main() {
-    let mut mgr = Manager::new(None)
+    let args = Args::parse();
+
+    /* the state file is only good for one restart */
+    let restore = args.restore.and_then(|path| {
+        let state = State::load(&path);
+        let _ = std::fs::remove_file(&path);
+
+        state.map_err(|e| eprintln!("failed to restore state: {}", e)).ok()
+    });
+
+    let mut mgr = Manager::new(None, restore)
         .expect("failed to spawn window manager");
 
     mgr.run()
         .expect("window manager encountered an error");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(x: i16, y: i16) -> Point {
+        Point {
+            x: x,
+            y: y,
+        }
+    }
+
+    fn tags() -> (Tags, TagSetId, TagSetId) {
+        let mut tags = Tags::new();
+        let a = tags.insert(TagSet::new(&["1", "2", "3"]));
+        let b = tags.insert(TagSet::new(&["a", "b"]));
+
+        (tags, a, b)
+    }
+
+    #[test]
+    fn test_hit() {
+        let (tags, a, b) = tags();
+        let info = MonitorInfo::connect(&Rect::new(100, 0, 200, 100));
+
+        /* two tagsets split the bar into two rows of 7px */
+        assert_eq!(info.hit(&tags, point(0, 0)), Some((a, 0)));
+        assert_eq!(info.hit(&tags, point(25, 6)), Some((a, 2)));
+        assert_eq!(info.hit(&tags, point(15, 7)), Some((b, 1)));
+
+        /* past the end of a shorter row, or past the grid */
+        assert_eq!(info.hit(&tags, point(25, 7)), None);
+        assert_eq!(info.hit(&tags, point(30, 0)), None);
+        assert_eq!(info.hit(&tags, point(-1, 0)), None);
+    }
+
+    #[test]
+    fn test_click() {
+        let (tags, a, _) = tags();
+        let info = MonitorInfo::connect(&Rect::new(0, 0, 200, 100));
+        let id = MonitorId::from(0);
+
+        let click = |focused, button, modifiers| {
+            info.click(&tags, id, focused, point(15, 0), button, modifiers)
+        };
+
+        assert!(matches!(
+            click(false, 1, keyboard::Modifier::empty()),
+            Some(Event::MonitorSet(m, t, Tag::On(1))) if m == id && t == a));
+        assert!(matches!(
+            click(true, 3, keyboard::Modifier::MOD4),
+            Some(Event::ClientUpdate(t, Tag::Toggle(1))) if t == a));
+
+        /* the focused client is only tagged from its own monitor's bar */
+        assert!(click(false, 1, keyboard::Modifier::MOD4).is_none());
+        assert!(click(false, 3, keyboard::Modifier::MOD4).is_none());
+
+        /* scrolling wraps around the row */
+        assert!(matches!(
+            click(false, 4, keyboard::Modifier::empty()),
+            Some(Event::MonitorSet(_, _, Tag::On(2)))));
+    }
+}
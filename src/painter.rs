@@ -1,3 +1,7 @@
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::hash::Hash;
+
 use crate::wm::Connection;
 use crate::error::Error;
 use crate::rect::Rect;
@@ -10,17 +14,264 @@ pub struct Color {
     pixel: u32,
 }
 
+impl Color {
+    #[inline]
+    pub fn pixel(&self) -> u32 {
+        self.pixel
+    }
+}
+
+/// The channel masks of a TrueColor visual, which let us compute pixels
+/// ourselves instead of asking the server to allocate them
+#[derive(Debug, Clone, Copy)]
+struct Masks {
+    red: u32,
+    green: u32,
+    blue: u32,
+}
+
+impl Masks {
+    /// Find the masks of a screen's root visual, if it is TrueColor
+    fn new(screen: &x::Screen) -> Option<Self> {
+        screen.allowed_depths()
+            .flat_map(|depth| depth.visuals())
+            .find(|visual| visual.visual_id() == screen.root_visual())
+            .filter(|visual| visual.class() == x::VisualClass::TrueColor)
+            .map(|visual| Masks {
+                red: visual.red_mask(),
+                green: visual.green_mask(),
+                blue: visual.blue_mask(),
+            })
+    }
+
+    /// Scale a 16-bit channel into a mask
+    fn channel(mask: u32, value: u16) -> u32 {
+        let bits = mask.count_ones().min(16);
+
+        if bits == 0 {
+            return 0;
+        }
+
+        ((value as u32) >> (16 - bits)) << mask.trailing_zeros()
+    }
+
+    fn pixel(&self, r: u16, g: u16, b: u16) -> u32 {
+        Masks::channel(self.red, r)
+            | Masks::channel(self.green, g)
+            | Masks::channel(self.blue, b)
+    }
+}
+
+/// A cached colour. Names the server does not know are cached without a
+/// colour, so they are not looked up again.
+#[derive(Debug, Clone, Copy)]
+struct Cached {
+    color: Option<Color>,
+    /* whether the colour holds a colormap entry, which has to be freed */
+    allocated: bool,
+    /* pinned colours are never evicted */
+    pinned: bool,
+}
+
+/// A bounded cache of colours. Once it is full, every colour which is not
+/// pinned is evicted, so colours which come and go such as those of the
+/// status text can not grow it forever.
+struct Cache<K> {
+    entries: HashMap<K, Cached>,
+    limit: usize,
+}
+
+impl<K: Hash + Eq> Cache<K> {
+    fn new(limit: usize) -> Self {
+        Cache {
+            entries: HashMap::new(),
+            limit: limit,
+        }
+    }
+
+    #[inline]
+    fn get<Q: Hash + Eq + ?Sized>(&self, key: &Q) -> Option<&Cached>
+    where
+        K: Borrow<Q>,
+    {
+        self.entries.get(key)
+    }
+
+    /// Cache a colour, returning the pixels of any colormap entries
+    /// evicted to make room, which need to be freed
+    fn insert(&mut self, key: K, cached: Cached) -> Vec<u32> {
+        let mut evicted = vec![];
+
+        if self.entries.len() >= self.limit {
+            evicted = self.entries.values()
+                .filter(|cached| cached.allocated && !cached.pinned)
+                .filter_map(|cached| cached.color.map(|color| color.pixel))
+                .collect();
+
+            self.entries.retain(|_, cached| cached.pinned);
+        }
+
+        self.entries.insert(key, cached);
+
+        evicted
+    }
+
+    fn pin(&mut self) {
+        for cached in self.entries.values_mut() {
+            cached.pinned = true;
+        }
+    }
+}
+
+/// Parse a colour of the form #rgb or #rrggbb into 16-bit components
+fn hex(spec: &str) -> Option<(u16, u16, u16)> {
+    let hex = spec.strip_prefix('#')?;
+
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let component = |s: &str| u16::from_str_radix(s, 16).ok();
+
+    match hex.len() {
+        3 => {
+            /* each digit is repeated to fill 16 bits, so #fff is white */
+            let r = component(&hex[0..1])?;
+            let g = component(&hex[1..2])?;
+            let b = component(&hex[2..3])?;
+
+            Some((r * 0x1111, g * 0x1111, b * 0x1111))
+        }
+        6 => {
+            let r = component(&hex[0..2])?;
+            let g = component(&hex[2..4])?;
+            let b = component(&hex[4..6])?;
+
+            Some((r * 0x101, g * 0x101, b * 0x101))
+        }
+        _ => None,
+    }
+}
+
+/// A core X font, along with the metrics needed to lay out text
+pub struct Font {
+    id: x::Font,
+    ascent: i16,
+    descent: i16,
+    /* the first single byte character in the font, and the width of
+     * each character from there on which has a glyph */
+    first: u16,
+    glyphs: Vec<Option<u16>>,
+}
+
+impl Font {
+    #[inline]
+    pub fn ascent(&self) -> i16 {
+        self.ascent
+    }
+
+    #[inline]
+    pub fn descent(&self) -> i16 {
+        self.descent
+    }
+
+    #[inline]
+    pub fn height(&self) -> u16 {
+        (self.ascent + self.descent) as u16
+    }
+
+    /// Check if the font has a glyph for a single byte character
+    pub fn has_glyph(&self, c: u8) -> bool {
+        self.advance(c).is_some()
+    }
+
+    /* get how far drawing a character moves along, if it has a glyph */
+    fn advance(&self, c: u8) -> Option<u16> {
+        (c as u16).checked_sub(self.first)
+            .and_then(|i| self.glyphs.get(i as usize))
+            .copied()
+            .flatten()
+    }
+
+    /// Encode a string for ImageText8. Only latin-1 characters can be
+    /// drawn with a core font, so anything else, or any character without a
+    /// glyph, is replaced with '?' (or a space if the font lacks that too).
+    pub fn encode(&self, text: &str) -> Vec<u8> {
+        let fallback = [b'?', b' '].iter()
+            .copied()
+            .find(|c| self.has_glyph(*c));
+
+        text.chars()
+            .filter_map(|c| {
+                let c = c as u32;
+
+                if c <= 0xff && self.has_glyph(c as u8) {
+                    Some(c as u8)
+                } else {
+                    fallback
+                }
+            })
+            /* ImageText8 is limited to 255 characters */
+            .take(255)
+            .collect()
+    }
+
+    /// Encode a string like `encode`, dropping whatever does not fit in a
+    /// width in pixels
+    pub fn fit(&self, text: &str, width: u16) -> Vec<u8> {
+        let mut x = 0u32;
+
+        self.encode(text)
+            .into_iter()
+            .take_while(|c| {
+                x += self.advance(*c).unwrap_or(0) as u32;
+                x <= width as u32
+            })
+            .collect()
+    }
+}
+
+/// An off-screen pixmap which is drawn into and then copied to a window in
+/// one go, so the window never shows a partially drawn frame
+pub struct Surface {
+    window: x::Window,
+    pixmap: x::Pixmap,
+    width: u16,
+    height: u16,
+}
+
+impl Surface {
+    #[inline]
+    pub fn window(&self) -> x::Window {
+        self.window
+    }
+
+    #[inline]
+    pub fn pixmap(&self) -> x::Pixmap {
+        self.pixmap
+    }
+}
+
 pub struct Painter {
     gc: x::Gcontext,
+    depth: u8,
     drawable: x::Drawable,
     colormap: x::Colormap,
+    masks: Option<Masks>,
+    /* colours are cached so each is only allocated once */
+    colors: Cache<(u16, u16, u16)>,
+    names: Cache<String>,
     black: Color,
     white: Color,
     foreground: Color,
     background: Color,
+    font: Option<x::Font>,
 }
 
 impl Painter {
+    /* the number of colours of each kind cached before evicting them */
+    const CACHE_LIMIT: usize = 64;
+
     pub fn new<T>(conn: &mut Connection<T>, window: x::Window) -> Result<Self, Error> {
         let gc: x::Gcontext = conn.generate_id();
         let drawable = x::Drawable::Window(window);
@@ -34,24 +285,111 @@ impl Painter {
         let white = Color { pixel: screen.white_pixel() };
         let black = Color { pixel: screen.black_pixel() };
         let colormap = screen.default_colormap();
+        let depth = screen.root_depth();
+        let masks = Masks::new(screen);
 
+        /* graphics exposures would send a NoExpose for every CopyArea */
         conn.send_and_check_request(&x::CreateGc {
             cid: gc,
             drawable: drawable,
-            value_list: &[],
+            value_list: &[
+                x::Gc::GraphicsExposures(false),
+            ],
         })?;
 
         Ok(Painter {
             gc: gc,
+            depth: depth,
             drawable: drawable,
             colormap: colormap,
+            masks: masks,
+            colors: Cache::new(Painter::CACHE_LIMIT),
+            names: Cache::new(Painter::CACHE_LIMIT),
             white: white,
             black: black,
             foreground: white,
             background: black,
+            font: None,
         })
     }
 
+    /// Set the drawable that all further drawing is done on. It must share
+    /// the root and depth of the window the painter was created with.
+    #[inline]
+    pub fn target(&mut self, drawable: x::Drawable) {
+        self.drawable = drawable;
+    }
+
+    /// Create an off-screen surface for a window
+    pub fn surface<T>(&mut self, conn: &mut Connection<T>, window: x::Window, width: u16, height: u16) -> Result<Surface, Error> {
+        let pixmap: x::Pixmap = conn.generate_id();
+
+        conn.send_and_check_request(&x::CreatePixmap {
+            depth: self.depth,
+            pid: pixmap,
+            drawable: x::Drawable::Window(window),
+            /* zero sized pixmaps are an error */
+            width: width.max(1),
+            height: height.max(1),
+        })?;
+
+        Ok(Surface {
+            window: window,
+            pixmap: pixmap,
+            width: width,
+            height: height,
+        })
+    }
+
+    /// Reallocate the pixmap behind a surface if its size has changed
+    pub fn resize<T>(&mut self, conn: &mut Connection<T>, surface: &mut Surface, width: u16, height: u16) -> Result<(), Error> {
+        if surface.width != width || surface.height != height {
+            let new = self.surface(conn, surface.window, width, height)?;
+            let old = std::mem::replace(surface, new);
+
+            self.free(conn, old)?;
+        }
+
+        Ok(())
+    }
+
+    /// Free the pixmap behind a surface. The window is left alone.
+    pub fn free<T>(&mut self, conn: &mut Connection<T>, surface: Surface) -> Result<(), Error> {
+        if self.drawable == x::Drawable::Pixmap(surface.pixmap) {
+            self.drawable = x::Drawable::Window(surface.window);
+        }
+
+        conn.send_and_check_request(&x::FreePixmap {
+            pixmap: surface.pixmap,
+        })?;
+
+        Ok(())
+    }
+
+    /// Start drawing a frame into a surface
+    #[inline]
+    pub fn begin(&mut self, surface: &Surface) {
+        self.target(x::Drawable::Pixmap(surface.pixmap));
+    }
+
+    /// Copy a finished frame from a surface to its window. Drawing requests
+    /// are not checked individually, so this is also where they are flushed.
+    pub fn present<T>(&mut self, conn: &mut Connection<T>, surface: &Surface) -> Result<(), Error> {
+        conn.send_request_unchecked(&x::CopyArea {
+            src_drawable: x::Drawable::Pixmap(surface.pixmap),
+            dst_drawable: x::Drawable::Window(surface.window),
+            gc: self.gc,
+            src_x: 0,
+            src_y: 0,
+            dst_x: 0,
+            dst_y: 0,
+            width: surface.width,
+            height: surface.height,
+        });
+
+        conn.flush()
+    }
+
     #[inline]
     pub fn white(&self) -> Color {
         self.white
@@ -62,49 +400,137 @@ impl Painter {
         self.black
     }
 
-    pub fn color<T>(&mut self, conn: &mut Connection<T>, r: u16, g: u16, b: u16) -> Result<Color, Error> {
-        let cookie = conn.send_request(&x::AllocColor {
+    /// Get a colour from 8-bit components
+    #[inline]
+    pub fn color<T>(&mut self, conn: &mut Connection<T>, r: u8, g: u8, b: u8) -> Result<Color, Error> {
+        self.rgb(conn, r as u16 * 0x101, g as u16 * 0x101, b as u16 * 0x101)
+    }
+
+    /// Get a colour from 16-bit components
+    pub fn rgb<T>(&mut self, conn: &mut Connection<T>, r: u16, g: u16, b: u16) -> Result<Color, Error> {
+        if let Some(color) = self.colors.get(&(r, g, b)).and_then(|cached| cached.color) {
+            return Ok(color);
+        }
+
+        let (color, allocated) = match self.masks {
+            Some(masks) => {
+                (Color { pixel: masks.pixel(r, g, b) }, false)
+            }
+            None => {
+                let cookie = conn.send_request(&x::AllocColor {
+                    cmap: self.colormap,
+                    red: r,
+                    green: g,
+                    blue: b,
+                });
+
+                let reply = conn.wait_for_reply(cookie)?;
+
+                (Color { pixel: reply.pixel() }, true)
+            }
+        };
+
+        let cached = Cached {
+            color: Some(color),
+            allocated: allocated,
+            pinned: false,
+        };
+
+        let evicted = self.colors.insert((r, g, b), cached);
+        self.free_colors(conn, &evicted)?;
+
+        Ok(color)
+    }
+
+    /// Get a colour from a string, which is either #rgb, #rrggbb, or the
+    /// name of a colour known to the X server
+    pub fn parse<T>(&mut self, conn: &mut Connection<T>, spec: &str) -> Result<Color, Error> {
+        if spec.starts_with('#') {
+            let (r, g, b) = hex(spec)
+                .ok_or_else(|| Error::InvalidColor(String::from(spec)))?;
+
+            return self.rgb(conn, r, g, b);
+        }
+
+        if let Some(cached) = self.names.get(spec) {
+            return cached.color.ok_or_else(|| Error::InvalidColor(String::from(spec)));
+        }
+
+        let cookie = conn.send_request(&x::AllocNamedColor {
             cmap: self.colormap,
-            red: r * 256,
-            green: g * 256,
-            blue: b * 256,
+            name: spec.as_bytes(),
         });
 
-        let reply = conn.wait_for_reply(cookie)?;
+        /* the server reports unknown names as a Name error */
+        let color = match conn.wait_for_reply(cookie) {
+            Ok(reply) => Some(Color { pixel: reply.pixel() }),
+            Err(Error::XCBError(xcb::Error::Protocol(xcb::ProtocolError::X(x::Error::Name(_), _)))) => None,
+            Err(e) => return Err(e),
+        };
+
+        let cached = Cached {
+            color: color,
+            allocated: color.is_some(),
+            pinned: false,
+        };
+
+        /* evict after allocating, so the new colour can not take the
+         * place of one which is still being drawn with */
+        let evicted = self.names.insert(String::from(spec), cached);
+        self.free_colors(conn, &evicted)?;
+
+        color.ok_or_else(|| Error::InvalidColor(String::from(spec)))
+    }
 
-        Ok(Color { pixel: reply.pixel() })
+    /// Keep every colour cached so far when the cache is full, such as
+    /// those of a colour scheme which are held onto for good
+    pub fn pin(&mut self) {
+        self.colors.pin();
+        self.names.pin();
+    }
+
+    fn free_colors<T>(&mut self, conn: &mut Connection<T>, pixels: &[u32]) -> Result<(), Error> {
+        if !pixels.is_empty() {
+            conn.send_and_check_request(&x::FreeColors {
+                cmap: self.colormap,
+                plane_mask: 0,
+                pixels: pixels,
+            })?;
+        }
+
+        Ok(())
     }
 
     pub fn brush<T>(&mut self, conn: &mut Connection<T>, foreground: Color, background: Color) -> Result<(), Error> {
         if self.foreground != foreground && self.background != background {
-            conn.send_and_check_request(&x::ChangeGc {
+            conn.send_request_unchecked(&x::ChangeGc {
                 gc: self.gc,
                 value_list: &[
                     x::Gc::Foreground(foreground.pixel),
                     x::Gc::Background(background.pixel),
                 ],
-            })?;
+            });
 
             self.foreground = foreground;
             self.background = background;
 
         } else if self.foreground != foreground {
-            conn.send_and_check_request(&x::ChangeGc {
+            conn.send_request_unchecked(&x::ChangeGc {
                 gc: self.gc,
                 value_list: &[
                     x::Gc::Foreground(foreground.pixel),
                 ],
-            })?;
+            });
 
             self.foreground = foreground;
 
         } else if self.background != background {
-            conn.send_and_check_request(&x::ChangeGc {
+            conn.send_request_unchecked(&x::ChangeGc {
                 gc: self.gc,
                 value_list: &[
                     x::Gc::Background(background.pixel),
                 ],
-            })?;
+            });
 
             self.background = background;
         };
@@ -115,12 +541,240 @@ impl Painter {
     pub fn rect<T>(&mut self, conn: &mut Connection<T>, rect: &Rect) -> Result<(), Error> {
         let area = x::Rectangle::from(rect);
 
-        conn.send_and_check_request(&x::PolyFillRectangle {
+        conn.send_request_unchecked(&x::PolyFillRectangle {
             drawable: self.drawable,
             gc: self.gc,
             rectangles: &[area],
+        });
+
+        Ok(())
+    }
+
+    /// Open a core X font by name, such as "fixed" or an XLFD pattern
+    pub fn font<T>(&mut self, conn: &mut Connection<T>, name: &str) -> Result<Font, Error> {
+        let id: x::Font = conn.generate_id();
+
+        conn.send_and_check_request(&x::OpenFont {
+            fid: id,
+            name: name.as_bytes(),
         })?;
 
+        let cookie = conn.send_request(&x::QueryFont {
+            font: x::Fontable::Font(id),
+        });
+
+        let reply = conn.wait_for_reply(cookie)?;
+
+        let first = reply.min_char_or_byte2();
+        let last = reply.max_char_or_byte2();
+
+        /* only the first row of a matrix font can be reached with
+         * single byte characters */
+        let glyphs = if reply.min_byte1() != 0 || first > last {
+            vec![]
+        } else if reply.char_infos().is_empty() {
+            /* all characters share the same metrics, so all exist */
+            let width = reply.max_bounds().character_width.max(0) as u16;
+            vec![Some(width); (last - first + 1) as usize]
+        } else {
+            /* non-existent characters have all zero metrics */
+            reply.char_infos()
+                .iter()
+                .take((last - first + 1) as usize)
+                .map(|info| {
+                    let exists = info.character_width != 0
+                        || info.left_side_bearing != 0
+                        || info.right_side_bearing != 0
+                        || info.ascent != 0
+                        || info.descent != 0;
+
+                    if exists {
+                        Some(info.character_width.max(0) as u16)
+                    } else {
+                        None
+                    }
+                })
+                .collect()
+        };
+
+        Ok(Font {
+            id: id,
+            ascent: reply.font_ascent(),
+            descent: reply.font_descent(),
+            first: first,
+            glyphs: glyphs,
+        })
+    }
+
+    /// Measure the width of a string in pixels as it would be drawn
+    pub fn measure<T>(&mut self, conn: &mut Connection<T>, font: &Font, text: &str) -> Result<u16, Error> {
+        let string: Vec<_> = font.encode(text)
+            .into_iter()
+            .map(|c| x::Char2b { byte1: 0, byte2: c })
+            .collect();
+
+        if string.is_empty() {
+            return Ok(0);
+        }
+
+        let cookie = conn.send_request(&x::QueryTextExtents {
+            font: x::Fontable::Font(font.id),
+            string: &string,
+        });
+
+        let reply = conn.wait_for_reply(cookie)?;
+
+        Ok(reply.overall_width().max(0) as u16)
+    }
+
+    /// Draw a string with the current brush, starting at the left edge of
+    /// rect and centered vertically within it. Characters which would
+    /// go past the right edge are left out.
+    pub fn text<T>(&mut self, conn: &mut Connection<T>, font: &Font, rect: &Rect, text: &str) -> Result<(), Error> {
+        if self.font != Some(font.id) {
+            conn.send_request_unchecked(&x::ChangeGc {
+                gc: self.gc,
+                value_list: &[
+                    x::Gc::Font(font.id),
+                ],
+            });
+
+            self.font = Some(font.id);
+        }
+
+        let string = font.fit(text, rect.w);
+        let baseline = rect.y + (rect.h as i16 + font.ascent - font.descent) / 2;
+
+        conn.send_request_unchecked(&x::ImageText8 {
+            drawable: self.drawable,
+            gc: self.gc,
+            x: rect.x,
+            y: baseline,
+            string: &string,
+        });
+
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hex() {
+        assert_eq!(hex("#1e1e2e"), Some((0x1e1e, 0x1e1e, 0x2e2e)));
+        assert_eq!(hex("#fff"), Some((0xffff, 0xffff, 0xffff)));
+        assert_eq!(hex("#f00"), Some((0xffff, 0, 0)));
+        assert_eq!(hex("1e1e2e"), None);
+        assert_eq!(hex("#1e1e2"), None);
+        assert_eq!(hex("#gggggg"), None);
+        assert_eq!(hex("#+1+1+1"), None);
+    }
+
+    /* a fixed width font covering printable latin-1, without some glyphs */
+    fn fixed(missing: &[u8]) -> Font {
+        let glyphs = (0x20..=0xffu16)
+            .map(|c| {
+                if missing.contains(&(c as u8)) {
+                    None
+                } else {
+                    Some(6)
+                }
+            })
+            .collect();
+
+        Font {
+            id: unsafe { xcb::XidNew::new(1) },
+            ascent: 10,
+            descent: 3,
+            first: 0x20,
+            glyphs: glyphs,
+        }
+    }
+
+    #[test]
+    fn test_encode() {
+        let font = fixed(b"b");
+
+        assert_eq!(font.encode("a c"), b"a c".to_vec());
+        assert!(!font.has_glyph(b'\n'));
+
+        /* missing glyphs and control characters are replaced */
+        assert_eq!(font.encode("abc"), b"a?c".to_vec());
+        assert_eq!(font.encode("a\tc"), b"a?c".to_vec());
+
+        /* latin-1 characters are one byte however they are encoded in
+         * utf-8, anything past that has no glyph */
+        assert_eq!(font.encode("\u{e9}"), vec![0xe9]);
+        assert_eq!(font.encode("\u{65e5}\u{672c}"), b"??".to_vec());
+
+        /* without '?' the fallback is a space */
+        let spaced = fixed(b"b?");
+        assert_eq!(spaced.encode("abc"), b"a c".to_vec());
+    }
+
+    #[test]
+    fn test_fit() {
+        let font = fixed(&[]);
+
+        assert_eq!(font.fit("hello", 30), b"hello".to_vec());
+        assert_eq!(font.fit("hello", 29), b"hell".to_vec());
+        assert_eq!(font.fit("hello", 5), vec![]);
+    }
+
+    #[test]
+    fn test_cache() {
+        let color = |pixel: u32, allocated: bool| Cached {
+            color: Some(Color { pixel: pixel }),
+            allocated: allocated,
+            pinned: false,
+        };
+
+        let mut cache = Cache::new(3);
+
+        assert!(cache.insert("scheme", color(1, true)).is_empty());
+        cache.pin();
+
+        assert!(cache.insert("a", color(2, true)).is_empty());
+        assert!(cache.insert("b", color(3, false)).is_empty());
+
+        /* unknown names are cached too */
+        let unknown = Cached {
+            color: None,
+            allocated: false,
+            pinned: false,
+        };
+
+        /* once full, only unpinned entries holding a colormap entry are
+         * handed back to be freed */
+        assert_eq!(cache.insert("unknown", unknown), vec![2]);
+        assert!(cache.get("scheme").is_some());
+        assert!(cache.get("a").is_none());
+        assert!(cache.get("b").is_none());
+        assert!(cache.get("unknown").unwrap().color.is_none());
+    }
+
+    #[test]
+    fn test_masks() {
+        /* the common 24-bit layout */
+        let masks = Masks {
+            red: 0xff0000,
+            green: 0x00ff00,
+            blue: 0x0000ff,
+        };
+
+        assert_eq!(masks.pixel(0x1e1e, 0x1e1e, 0x2e2e), 0x1e1e2e);
+        assert_eq!(masks.pixel(0xffff, 0, 0), 0xff0000);
+
+        /* 16-bit rgb565 */
+        let masks = Masks {
+            red: 0xf800,
+            green: 0x07e0,
+            blue: 0x001f,
+        };
+
+        assert_eq!(masks.pixel(0xffff, 0xffff, 0xffff), 0xffff);
+        assert_eq!(masks.pixel(0, 0xffff, 0), 0x07e0);
+    }
+}
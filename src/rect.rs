@@ -24,6 +24,15 @@ pub enum Corner {
     BottomRight,
 }
 
+/// A direction on the screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
 pub enum Cut {
     Horizontal(u16),
     Vertical(u16),
@@ -147,12 +156,115 @@ impl Rect {
         }
     }
 
+    /// Move each edge of the rect inwards. Edges never cross, so a rect
+    /// which is too small collapses to zero width or height.
+    pub fn shrink(&self, top: u16, right: u16, bottom: u16, left: u16) -> Rect {
+        let left = left.min(self.w);
+        let right = right.min(self.w - left);
+        let top = top.min(self.h);
+        let bottom = bottom.min(self.h - top);
+
+        Rect::new(
+            self.x + left as i16,
+            self.y + top as i16,
+            self.w - left - right,
+            self.h - top - bottom)
+    }
+
+    /// Move every edge of the rect inwards by the same amount
+    #[inline]
+    pub fn inset(&self, n: u16) -> Rect {
+        self.shrink(n, n, n, n)
+    }
+
+    /// Move each edge of the rect outwards, the reverse of `shrink`
+    pub fn grow(&self, top: u16, right: u16, bottom: u16, left: u16) -> Rect {
+        let max = i16::MAX as u16;
+        let top = top.min(max);
+        let left = left.min(max);
+
+        Rect::new(
+            self.x.saturating_sub(left as i16),
+            self.y.saturating_sub(top as i16),
+            self.w.saturating_add(left).saturating_add(right).min(max),
+            self.h.saturating_add(top).saturating_add(bottom).min(max))
+    }
+
+    /// Move every edge of the rect outwards by the same amount
+    #[inline]
+    pub fn expand(&self, n: u16) -> Rect {
+        self.grow(n, n, n, n)
+    }
+
     pub fn split<'a>(&'a self, at: Split) -> SplitIterator<'a> {
         match at {
             Split::Horizontal(n) => SplitIterator::Horizontal(HorizontalSplit::new(self, n)),
             Split::Vertical(n) => SplitIterator::Vertical(VerticalSplit::new(self, n)),
         }
     }
+
+    /// Get how far another rect lies past the edge of this one facing a
+    /// direction. Rects whose centre is not further in that direction are
+    /// not beyond the edge at all, and overlapping rects are at distance 0.
+    pub fn distance(&self, other: &Rect, direction: Direction) -> Option<u16> {
+        let (from, to) = (self.center(), other.center());
+
+        let (beyond, gap) = match direction {
+            Direction::Left => (to.x < from.x, self.left() as i32 - other.right() as i32),
+            Direction::Right => (to.x > from.x, other.left() as i32 - self.right() as i32),
+            Direction::Up => (to.y < from.y, self.top() as i32 - other.bottom() as i32),
+            Direction::Down => (to.y > from.y, other.top() as i32 - self.bottom() as i32),
+        };
+
+        if beyond {
+            Some(gap.clamp(0, u16::MAX as i32) as u16)
+        } else {
+            None
+        }
+    }
+
+    /// Get how much two rects overlap across a direction, which is the
+    /// shared height when moving left or right and the shared width when
+    /// moving up or down
+    pub fn overlap(&self, other: &Rect, direction: Direction) -> u16 {
+        let (start, end) = match direction {
+            Direction::Left | Direction::Right => (
+                self.top().max(other.top()),
+                self.bottom().min(other.bottom()),
+            ),
+            Direction::Up | Direction::Down => (
+                self.left().max(other.left()),
+                self.right().min(other.right()),
+            ),
+        };
+
+        (end as i32 - start as i32).max(0) as u16
+    }
+
+    /// Check whether another rect lies in a direction from this one while
+    /// sharing part of the edge facing it
+    #[inline]
+    pub fn adjacent(&self, other: &Rect, direction: Direction) -> bool {
+        self.distance(other, direction).is_some() && self.overlap(other, direction) > 0
+    }
+
+    /// Find the nearest adjacent rect in a direction, preferring the one
+    /// sharing more of the edge when two are as near
+    pub fn nearest<T, I>(&self, direction: Direction, rects: I) -> Option<T>
+    where
+        I: IntoIterator<Item = (T, Rect)>,
+    {
+        rects.into_iter()
+            .filter(|(_, rect)| self.adjacent(rect, direction))
+            .map(|(value, rect)| {
+                let distance = self.distance(&rect, direction).unwrap_or(u16::MAX);
+                let overlap = self.overlap(&rect, direction);
+
+                (distance, std::cmp::Reverse(overlap), value)
+            })
+            .min_by_key(|(distance, overlap, _)| (*distance, *overlap))
+            .map(|(_, _, value)| value)
+    }
 }
 
 impl Contains<Point> for Rect {
@@ -426,3 +538,75 @@ mod split_tests {
         assert!(col.next().is_none());
     }
 }
+
+#[cfg(test)]
+mod inset_tests {
+    use super::*;
+
+    #[test]
+    fn test_inset() {
+        let rect = Rect::new(10, 20, 100, 50);
+
+        assert_eq!(rect.inset(0), rect);
+        assert_eq!(rect.inset(5), Rect::new(15, 25, 90, 40));
+        assert_eq!(rect.shrink(1, 2, 3, 4), Rect::new(14, 21, 94, 46));
+    }
+
+    #[test]
+    fn test_inset_collapse() {
+        let rect = Rect::new(0, 0, 10, 4);
+
+        assert_eq!(rect.inset(3), Rect::new(3, 3, 4, 0));
+        assert_eq!(rect.inset(20), Rect::new(10, 4, 0, 0));
+    }
+
+    #[test]
+    fn test_expand() {
+        let rect = Rect::new(15, 25, 90, 40);
+
+        assert_eq!(rect.expand(5), Rect::new(10, 20, 100, 50));
+        assert_eq!(rect.expand(5).inset(5), rect);
+        assert_eq!(Rect::new(0, 0, 1, 1).expand(2), Rect::new(-2, -2, 5, 5));
+
+        /* uneven edges undo the matching shrink */
+        assert_eq!(rect.grow(1, 2, 3, 4), Rect::new(11, 24, 96, 44));
+        assert_eq!(rect.grow(1, 2, 3, 4).shrink(1, 2, 3, 4), rect);
+    }
+}
+
+#[cfg(test)]
+mod direction_tests {
+    use super::*;
+
+    #[test]
+    fn test_distance() {
+        let rect = Rect::new(0, 0, 50, 50);
+        let right = Rect::new(60, 10, 50, 50);
+
+        assert_eq!(rect.distance(&right, Direction::Right), Some(10));
+        assert_eq!(right.distance(&rect, Direction::Left), Some(10));
+        assert_eq!(rect.distance(&right, Direction::Left), None);
+        assert_eq!(rect.distance(&right, Direction::Down), Some(0));
+
+        assert_eq!(rect.overlap(&right, Direction::Right), 40);
+        assert_eq!(rect.overlap(&right, Direction::Down), 0);
+
+        assert!(rect.adjacent(&right, Direction::Right));
+        assert!(!rect.adjacent(&right, Direction::Down));
+    }
+
+    #[test]
+    fn test_nearest() {
+        /* a master on the left and a stack of two on the right */
+        let master = Rect::new(0, 0, 50, 100);
+        let top = Rect::new(50, 0, 50, 30);
+        let bottom = Rect::new(50, 30, 50, 70);
+        let rects = vec![(0, master), (1, top), (2, bottom)];
+
+        assert_eq!(master.nearest(Direction::Right, rects.clone()), Some(2));
+        assert_eq!(top.nearest(Direction::Left, rects.clone()), Some(0));
+        assert_eq!(top.nearest(Direction::Down, rects.clone()), Some(2));
+        assert_eq!(bottom.nearest(Direction::Up, rects.clone()), Some(1));
+        assert_eq!(master.nearest(Direction::Left, rects), None);
+    }
+}
@@ -0,0 +1,227 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use crate::error::Error;
+use crate::slab::AsIndex;
+use crate::tag::{TagMask, TagSetId};
+
+use bitvec::prelude::*;
+use xcb::{x, Xid, XidNew};
+
+/// The saved state of a single client
+pub struct ClientState {
+    pub window: x::Window,
+    pub monitor: String,
+    pub masks: Vec<(TagSetId, TagMask)>,
+}
+
+/// State handed from one window manager process to the next across an
+/// in-place restart. Clients are stored in tree order for each monitor, and
+/// monitors are referred to by name since their ids are not stable.
+///
+/// The state is stored as plain text, one record per line:
+///
+///     tagset <id> <mask>
+///     focus <monitor> <window>
+///     client <window> <monitor> [<id>:<mask> ...]
+///
+/// where masks are written as strings of 0s and 1s.
+pub struct State {
+    pub tagsets: Vec<(TagSetId, TagMask)>,
+    pub focus: Vec<(String, x::Window)>,
+    pub clients: Vec<ClientState>,
+}
+
+fn write_mask(out: &mut String, mask: &TagMask) {
+    for bit in mask.iter() {
+        out.push(if *bit { '1' } else { '0' });
+    }
+}
+
+fn parse_mask(s: &str) -> Result<TagMask, Error> {
+    let bits: Result<BitVec, Error> = s.chars()
+        .map(|c| match c {
+            '0' => Ok(false),
+            '1' => Ok(true),
+            _ => Err(Error::InvalidState),
+        })
+        .collect();
+
+    Ok(TagMask::from(bits?))
+}
+
+fn parse_window(s: &str) -> Result<x::Window, Error> {
+    let id = s.parse::<u32>()
+        .map_err(|_| Error::InvalidState)?;
+
+    Ok(unsafe { x::Window::new(id) })
+}
+
+fn parse_tagset(s: &str) -> Result<TagSetId, Error> {
+    s.parse::<usize>()
+        .map(TagSetId::from)
+        .map_err(|_| Error::InvalidState)
+}
+
+impl State {
+    pub fn new() -> Self {
+        State {
+            tagsets: vec![],
+            focus: vec![],
+            clients: vec![],
+        }
+    }
+
+    /// Get the saved state of a window, if it has one
+    pub fn client(&self, window: x::Window) -> Option<&ClientState> {
+        self.clients.iter().find(|c| c.window == window)
+    }
+
+    /// Get the saved focus of a monitor, by name
+    pub fn focus(&self, monitor: &str) -> Option<x::Window> {
+        self.focus.iter()
+            .find(|(name, _)| name == monitor)
+            .map(|(_, window)| *window)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        fs::write(path, self.serialize())?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let text = fs::read_to_string(path)?;
+        State::parse(&text)
+    }
+
+    fn serialize(&self) -> String {
+        let mut out = String::new();
+
+        for (id, mask) in self.tagsets.iter() {
+            write!(out, "tagset {} ", id.as_index()).unwrap();
+            write_mask(&mut out, mask);
+            out.push('\n');
+        }
+
+        for (monitor, window) in self.focus.iter() {
+            writeln!(out, "focus {} {}", monitor, window.resource_id()).unwrap();
+        }
+
+        for client in self.clients.iter() {
+            write!(out, "client {} {}", client.window.resource_id(), client.monitor).unwrap();
+
+            for (id, mask) in client.masks.iter() {
+                write!(out, " {}:", id.as_index()).unwrap();
+                write_mask(&mut out, mask);
+            }
+
+            out.push('\n');
+        }
+
+        out
+    }
+
+    fn parse(text: &str) -> Result<Self, Error> {
+        let mut state = State::new();
+
+        for line in text.lines() {
+            let mut fields = line.split_whitespace();
+
+            match fields.next() {
+                Some("tagset") => {
+                    let id = parse_tagset(fields.next().ok_or(Error::InvalidState)?)?;
+                    let mask = parse_mask(fields.next().ok_or(Error::InvalidState)?)?;
+
+                    state.tagsets.push((id, mask));
+                }
+                Some("focus") => {
+                    let monitor = fields.next().ok_or(Error::InvalidState)?;
+                    let window = parse_window(fields.next().ok_or(Error::InvalidState)?)?;
+
+                    state.focus.push((String::from(monitor), window));
+                }
+                Some("client") => {
+                    let window = parse_window(fields.next().ok_or(Error::InvalidState)?)?;
+                    let monitor = fields.next().ok_or(Error::InvalidState)?;
+                    let mut masks = vec![];
+
+                    for field in fields {
+                        let (id, mask) = field.split_once(':')
+                            .ok_or(Error::InvalidState)?;
+
+                        masks.push((parse_tagset(id)?, parse_mask(mask)?));
+                    }
+
+                    state.clients.push(ClientState {
+                        window: window,
+                        monitor: String::from(monitor),
+                        masks: masks,
+                    });
+                }
+                None => {}
+                Some(_) => {
+                    return Err(Error::InvalidState);
+                }
+            }
+        }
+
+        Ok(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tag::Tag;
+
+    fn mask(tags: &[usize]) -> TagMask {
+        let mut mask = TagMask::new();
+        mask.clear();
+
+        for tag in tags {
+            mask.set(Tag::On(*tag));
+        }
+
+        mask
+    }
+
+    fn bits(mask: &TagMask) -> Vec<bool> {
+        mask.iter().map(|b| *b).collect()
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let window = unsafe { x::Window::new(0x400001) };
+
+        let mut state = State::new();
+        state.tagsets.push((TagSetId::from(0), mask(&[0, 2])));
+        state.focus.push((String::from("DP-1"), window));
+        state.clients.push(ClientState {
+            window: window,
+            monitor: String::from("DP-1"),
+            masks: vec![(TagSetId::from(1), mask(&[3]))],
+        });
+
+        let parsed = State::parse(&state.serialize()).unwrap();
+
+        assert_eq!(parsed.tagsets.len(), 1);
+        assert_eq!(parsed.tagsets[0].0, TagSetId::from(0));
+        assert_eq!(bits(&parsed.tagsets[0].1), vec![true, false, true]);
+
+        assert_eq!(parsed.focus("DP-1"), Some(window));
+        assert_eq!(parsed.focus("HDMI-1"), None);
+
+        let client = parsed.client(window).unwrap();
+        assert_eq!(client.monitor, "DP-1");
+        assert_eq!(client.masks[0].0, TagSetId::from(1));
+        assert_eq!(bits(&client.masks[0].1), vec![false, false, false, true]);
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(State::parse("bogus 1 2").is_err());
+        assert!(State::parse("tagset 0 012").is_err());
+        assert!(State::parse("client abc DP-1").is_err());
+    }
+}
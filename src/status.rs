@@ -0,0 +1,118 @@
+/// A run of status text drawn with the same colours. A colour of None
+/// means the bar's default colour.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Segment<'a> {
+    pub foreground: Option<&'a str>,
+    pub background: Option<&'a str>,
+    pub text: &'a str,
+}
+
+/// Split status text into segments on its inline colour escapes:
+///
+///     ^c<color>^  set the foreground colour
+///     ^b<color>^  set the background colour
+///     ^d^         reset both colours to the default
+///
+/// where a colour is anything Painter::parse accepts. Anything else between
+/// a pair of '^' is kept as text.
+pub fn parse(text: &str) -> Vec<Segment<'_>> {
+    let mut segments = vec![];
+    let mut foreground = None;
+    let mut background = None;
+
+    /* text before mark has been consumed, and escapes are searched for
+     * from cursor onwards */
+    let mut mark = 0;
+    let mut cursor = 0;
+
+    while let Some(open) = text[cursor..].find('^').map(|i| cursor + i) {
+        let close = match text[open + 1..].find('^') {
+            Some(i) => open + 1 + i,
+            None => break,
+        };
+
+        let escape = &text[open + 1..close];
+        let kind = escape.chars().next().unwrap_or(' ');
+        let color = &escape[kind.len_utf8().min(escape.len())..];
+
+        let valid = match kind {
+            'c' | 'b' => !color.is_empty() && !color.contains(char::is_whitespace),
+            'd' => color.is_empty(),
+            _ => false,
+        };
+
+        if !valid {
+            /* not an escape, so keep the '^' as text */
+            cursor = open + 1;
+            continue;
+        }
+
+        if open > mark {
+            segments.push(Segment {
+                foreground: foreground,
+                background: background,
+                text: &text[mark..open],
+            });
+        }
+
+        match kind {
+            'c' => foreground = Some(color),
+            'b' => background = Some(color),
+            _ => {
+                foreground = None;
+                background = None;
+            }
+        }
+
+        mark = close + 1;
+        cursor = mark;
+    }
+
+    if mark < text.len() {
+        segments.push(Segment {
+            foreground: foreground,
+            background: background,
+            text: &text[mark..],
+        });
+    }
+
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment<'a>(fg: Option<&'a str>, bg: Option<&'a str>, text: &'a str) -> Segment<'a> {
+        Segment {
+            foreground: fg,
+            background: bg,
+            text: text,
+        }
+    }
+
+    #[test]
+    fn test_plain() {
+        assert_eq!(parse("hello"), vec![segment(None, None, "hello")]);
+        assert!(parse("").is_empty());
+    }
+
+    #[test]
+    fn test_escapes() {
+        assert_eq!(
+            parse("cpu ^c#ff0000^90%^d^ | ^b#000000^mem"),
+            vec![
+                segment(None, None, "cpu "),
+                segment(Some("#ff0000"), None, "90%"),
+                segment(None, None, " | "),
+                segment(None, Some("#000000"), "mem"),
+            ]);
+    }
+
+    #[test]
+    fn test_literal_caret() {
+        assert_eq!(parse("a ^ b"), vec![segment(None, None, "a ^ b")]);
+        assert_eq!(parse("2^8 = 256 ^ x"), vec![segment(None, None, "2^8 = 256 ^ x")]);
+        assert_eq!(parse("^d^x^"), vec![segment(None, None, "x^")]);
+    }
+}
@@ -206,6 +206,12 @@ impl Tags {
         TagSetId { inner: self.tagsets.insert(tagset) }
     }
 
+    /// Remove a tagset, after the monitor using it is gone. Its id may be
+    /// reused by the next insert.
+    pub fn remove(&mut self, id: TagSetId) -> TagSet {
+        self.tagsets.remove(id.inner)
+    }
+
     pub fn visible(&self, id: TagSetId, selection: &TagMask) -> bool {
         self.tagsets[id.inner].mask.visible(selection)
     }
@@ -259,4 +265,63 @@ impl<'a, 'b> TagSelection<'a, 'b> {
             .iter()
             .map(move |x| (*x, &self.tags.tagsets[x.inner]))
     }
+
+    /// Get the tags currently selected in each tagset
+    pub fn view(&self) -> View {
+        let tags = self.iter()
+            .map(|(id, tagset)| {
+                let enabled = tagset.mask().iter()
+                    .enumerate()
+                    .filter(|(_, enabled)| **enabled)
+                    .map(|(i, _)| i)
+                    .collect();
+
+                (id, enabled)
+            })
+            .collect();
+
+        View {
+            tags: tags,
+        }
+    }
+}
+
+/// The tags enabled in each tagset of a selection at one point in time.
+/// Masks of different lengths with the same tags enabled are equal views.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct View {
+    tags: Vec<(TagSetId, Vec<usize>)>,
+}
+
+impl View {
+    /// Check whether the view selects tags from a tagset
+    pub fn contains(&self, id: TagSetId) -> bool {
+        self.tags.iter().any(|(tid, _)| *tid == id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_view() {
+        let mut tags = Tags::new();
+        let id = tags.insert(TagSet::new(&["a", "b", "c"]));
+        let ids = [id];
+
+        let first = tags.select(&ids).view();
+
+        /* clearing the mask shrinks it, which should not matter */
+        tags[id].mask_mut().clear();
+        tags[id].mask_mut().set(Tag::On(1));
+
+        let second = tags.select(&ids).view();
+        assert_ne!(first, second);
+
+        tags[id].mask_mut().clear();
+        tags[id].mask_mut().set(Tag::On(0));
+
+        assert_eq!(tags.select(&ids).view(), first);
+    }
 }
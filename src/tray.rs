@@ -0,0 +1,496 @@
+use crate::atom::Atoms;
+use crate::error::Error;
+use crate::rect::Rect;
+use crate::wm::Connection;
+
+use xcb::{x, Xid};
+
+/* opcodes from the freedesktop system tray specification */
+const SYSTEM_TRAY_REQUEST_DOCK: u32 = 0;
+
+/* messages and flags from the xembed specification */
+const XEMBED_EMBEDDED_NOTIFY: u32 = 0;
+const XEMBED_MAPPED: u32 = 1 << 0;
+const XEMBED_VERSION: u32 = 0;
+
+struct Icon {
+    window: x::Window,
+    /* the size the icon asked for, used to keep its aspect ratio */
+    width: u16,
+    height: u16,
+    mapped: bool,
+}
+
+/* icons belong to other clients and can be destroyed at any time, before
+ * we see their DestroyNotify. A BadWindow from a request to an icon only
+ * means it is gone, so it is turned into None rather than an error. */
+fn alive<R>(result: Result<R, Error>) -> Result<Option<R>, Error> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(e) if e.is_bad_window() => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+impl Icon {
+    /// Get the width of the icon when scaled to a given height
+    fn scale(&self, size: u16) -> u16 {
+        if self.height == 0 || self.width == self.height {
+            size
+        } else {
+            let width = self.width as u32 * size as u32 / self.height as u32;
+            width.clamp(1, u16::MAX as u32) as u16
+        }
+    }
+}
+
+/// A system tray, which embeds icons from other clients into a container
+/// window using XEmbed. Icons are laid out from left to right in docking
+/// order, and the container is placed at the right edge of an area given
+/// to us, normally the primary monitor's bar.
+pub struct Tray {
+    window: x::Window,
+    selection: x::Atom,
+    icons: Vec<Icon>,
+    rect: Rect,
+}
+
+impl Tray {
+    /// Create the tray and take ownership of the _NET_SYSTEM_TRAY_S<n>
+    /// selection for our screen. If another tray already owns it, no tray
+    /// is created.
+    pub fn new<T>(conn: &mut Connection<T>, atoms: &Atoms) -> Result<Option<Self>, Error> {
+        let name = format!("_NET_SYSTEM_TRAY_S{}", conn.screen());
+        let cookie = conn.send_request(&x::InternAtom {
+            only_if_exists: false,
+            name: name.as_bytes(),
+        });
+
+        let selection = conn.wait_for_reply(cookie)?.atom();
+
+        let cookie = conn.send_request(&x::GetSelectionOwner {
+            selection: selection,
+        });
+
+        if !conn.wait_for_reply(cookie)?.owner().is_none() {
+            return Ok(None);
+        }
+
+        let screen = conn.get_setup()
+            .roots()
+            .nth(conn.screen())
+            .ok_or(Error::MissingScreen)?;
+
+        let window: x::Window = conn.generate_id();
+        let visual = screen.root_visual();
+        let background = screen.black_pixel();
+        let root = conn.root();
+
+        /* icons are children of the container, so redirecting its
+         * substructure lets us decide their size and visibility */
+        conn.send_and_check_request(&x::CreateWindow {
+            depth: x::COPY_FROM_PARENT as u8,
+            wid: window,
+            parent: root,
+            x: 0,
+            y: 0,
+            width: 1,
+            height: 1,
+            border_width: 0,
+            class: x::WindowClass::InputOutput,
+            visual: visual,
+            value_list: &[
+                x::Cw::BackPixel(background),
+                x::Cw::OverrideRedirect(true),
+                x::Cw::EventMask(
+                    x::EventMask::SUBSTRUCTURE_NOTIFY
+                        | x::EventMask::SUBSTRUCTURE_REDIRECT,
+                ),
+            ],
+        })?;
+
+        /* icons are always laid out horizontally */
+        conn.send_and_check_request(&x::ChangeProperty {
+            mode: x::PropMode::Replace,
+            window: window,
+            property: atoms.net_system_tray_orientation,
+            r#type: x::ATOM_CARDINAL,
+            data: &[0u32],
+        })?;
+
+        conn.send_and_check_request(&x::SetSelectionOwner {
+            owner: window,
+            selection: selection,
+            time: x::CURRENT_TIME,
+        })?;
+
+        let cookie = conn.send_request(&x::GetSelectionOwner {
+            selection: selection,
+        });
+
+        if conn.wait_for_reply(cookie)?.owner() != window {
+            conn.send_and_check_request(&x::DestroyWindow {
+                window: window,
+            })?;
+
+            return Ok(None);
+        }
+
+        /* let clients waiting for a tray know that one is available */
+        let event = x::ClientMessageEvent::new(
+            root,
+            atoms.manager,
+            x::ClientMessageData::Data32([
+                x::CURRENT_TIME,
+                selection.resource_id(),
+                window.resource_id(),
+                0,
+                0,
+            ]),
+        );
+
+        conn.send_and_check_request(&x::SendEvent {
+            propagate: false,
+            destination: x::SendEventDest::Window(root),
+            event_mask: x::EventMask::STRUCTURE_NOTIFY,
+            event: &event,
+        })?;
+
+        Ok(Some(Tray {
+            window: window,
+            selection: selection,
+            icons: vec![],
+            rect: Rect::new(0, 0, 0, 0),
+        }))
+    }
+
+    #[inline]
+    pub fn window(&self) -> x::Window {
+        self.window
+    }
+
+    #[inline]
+    pub fn selection(&self) -> x::Atom {
+        self.selection
+    }
+
+    #[inline]
+    pub fn contains(&self, window: x::Window) -> bool {
+        self.icons.iter().any(|icon| icon.window == window)
+    }
+
+    /// Get the width taken up by the tray in the area it is placed in
+    pub fn width(&self) -> u16 {
+        let size = self.rect.h;
+        let width: u32 = self.icons.iter()
+            .filter(|icon| icon.mapped)
+            .map(|icon| icon.scale(size) as u32)
+            .sum();
+
+        width.min(self.rect.w as u32) as u16
+    }
+
+    /// Check whether a client message is a request to dock an icon, and
+    /// return the icon's window if so
+    pub fn request(&self, atoms: &Atoms, e: &x::ClientMessageEvent) -> Option<x::Window> {
+        if e.r#type() != atoms.net_system_tray_opcode {
+            return None;
+        }
+
+        match e.data() {
+            x::ClientMessageData::Data32(data) if data[1] == SYSTEM_TRAY_REQUEST_DOCK => {
+                Some(unsafe { xcb::XidNew::new(data[2]) })
+            }
+            _ => None,
+        }
+    }
+
+    /// Embed an icon into the tray
+    pub fn dock<T>(&mut self, conn: &mut Connection<T>, atoms: &Atoms, window: x::Window) -> Result<(), Error> {
+        if self.contains(window) {
+            return Ok(());
+        }
+
+        if let Some(icon) = alive(self.embed(conn, atoms, window))? {
+            self.icons.push(icon);
+        }
+
+        self.arrange(conn)
+    }
+
+    fn embed<T>(&self, conn: &mut Connection<T>, atoms: &Atoms, window: x::Window) -> Result<Icon, Error> {
+        let cookie = conn.send_request(&x::GetGeometry {
+            drawable: x::Drawable::Window(window),
+        });
+
+        let geometry = conn.wait_for_reply(cookie)?;
+        let mapped = Tray::mapped(conn, atoms, window)?;
+
+        conn.send_and_check_request(&x::ChangeWindowAttributes {
+            window: window,
+            value_list: &[x::Cw::EventMask(
+                x::EventMask::STRUCTURE_NOTIFY
+                    | x::EventMask::PROPERTY_CHANGE,
+            )],
+        })?;
+
+        /* keep the icon alive if we exit before its owner does */
+        conn.send_and_check_request(&x::ChangeSaveSet {
+            mode: x::SetMode::Insert,
+            window: window,
+        })?;
+
+        conn.send_and_check_request(&x::ReparentWindow {
+            window: window,
+            parent: self.window,
+            x: 0,
+            y: 0,
+        })?;
+
+        let event = x::ClientMessageEvent::new(
+            window,
+            atoms.xembed,
+            x::ClientMessageData::Data32([
+                x::CURRENT_TIME,
+                XEMBED_EMBEDDED_NOTIFY,
+                0,
+                self.window.resource_id(),
+                XEMBED_VERSION,
+            ]),
+        );
+
+        conn.send_and_check_request(&x::SendEvent {
+            propagate: false,
+            destination: x::SendEventDest::Window(window),
+            event_mask: x::EventMask::NO_EVENT,
+            event: &event,
+        })?;
+
+        Ok(Icon {
+            window: window,
+            width: geometry.width(),
+            height: geometry.height(),
+            mapped: mapped,
+        })
+    }
+
+    /// Forget an icon, which has been destroyed or taken by another window.
+    /// Returns false if the window is not an icon.
+    pub fn remove(&mut self, window: x::Window) -> bool {
+        let len = self.icons.len();
+        self.icons.retain(|icon| icon.window != window);
+
+        self.icons.len() != len
+    }
+
+    /// Handle a request from an icon to change its size. Icons are always
+    /// the height of the tray, so only the aspect ratio is kept.
+    pub fn configure<T>(&mut self, conn: &mut Connection<T>, e: &x::ConfigureRequestEvent) -> Result<(), Error> {
+        let mask = e.value_mask();
+
+        if let Some(icon) = self.icons.iter_mut().find(|icon| icon.window == e.window()) {
+            if mask.contains(x::ConfigWindowMask::WIDTH) {
+                icon.width = e.width();
+            }
+
+            if mask.contains(x::ConfigWindowMask::HEIGHT) {
+                icon.height = e.height();
+            }
+        }
+
+        self.arrange(conn)
+    }
+
+    /// Handle a change to an icon's _XEMBED_INFO, which says whether it
+    /// wants to be shown
+    pub fn update<T>(&mut self, conn: &mut Connection<T>, atoms: &Atoms, window: x::Window) -> Result<(), Error> {
+        match alive(Tray::mapped(conn, atoms, window))? {
+            Some(mapped) => {
+                if let Some(icon) = self.icons.iter_mut().find(|icon| icon.window == window) {
+                    icon.mapped = mapped;
+                }
+            }
+            None => {
+                self.remove(window);
+            }
+        }
+
+        self.arrange(conn)
+    }
+
+    /// Set the colour behind the icons, which should match the bar
+    pub fn set_background<T>(&mut self, conn: &mut Connection<T>, pixel: u32) -> Result<(), Error> {
+        conn.send_and_check_request(&x::ChangeWindowAttributes {
+            window: self.window,
+            value_list: &[x::Cw::BackPixel(pixel)],
+        })?;
+
+        conn.send_and_check_request(&x::ClearArea {
+            exposures: true,
+            window: self.window,
+            x: 0,
+            y: 0,
+            width: 0,
+            height: 0,
+        })?;
+
+        Ok(())
+    }
+
+    /// Place the tray at the right edge of an area, with icons as tall as
+    /// the area
+    pub fn place<T>(&mut self, conn: &mut Connection<T>, rect: &Rect) -> Result<(), Error> {
+        self.rect = *rect;
+        self.arrange(conn)
+    }
+
+    /// Give the selection and all icons back, after another tray has
+    /// taken over
+    pub fn destroy<T>(self, conn: &mut Connection<T>) -> Result<(), Error> {
+        let root = conn.root();
+
+        for icon in self.icons.iter() {
+            alive(conn.send_and_check_request(&x::ReparentWindow {
+                window: icon.window,
+                parent: root,
+                x: 0,
+                y: 0,
+            }).map_err(Error::from))?;
+        }
+
+        conn.send_and_check_request(&x::DestroyWindow {
+            window: self.window,
+        })?;
+
+        Ok(())
+    }
+
+    /// Hand all icons back to the root window before we exit for a
+    /// restart. They are taken out of the save-set and unmapped, so the
+    /// server does not map them at the root where the next instance would
+    /// adopt them as clients. They dock again when it takes the selection.
+    pub fn release<T>(&self, conn: &mut Connection<T>) -> Result<(), Error> {
+        let root = conn.root();
+
+        for icon in self.icons.iter() {
+            alive(Tray::unembed(conn, root, icon.window))?;
+        }
+
+        Ok(())
+    }
+
+    fn unembed<T>(conn: &mut Connection<T>, root: x::Window, window: x::Window) -> Result<(), Error> {
+        conn.send_and_check_request(&x::ChangeSaveSet {
+            mode: x::SetMode::Delete,
+            window: window,
+        })?;
+
+        conn.send_and_check_request(&x::UnmapWindow {
+            window: window,
+        })?;
+
+        conn.send_and_check_request(&x::ReparentWindow {
+            window: window,
+            parent: root,
+            x: 0,
+            y: 0,
+        })?;
+
+        Ok(())
+    }
+
+    /// Read the XEMBED_MAPPED flag of an icon. Icons without _XEMBED_INFO
+    /// are always shown.
+    fn mapped<T>(conn: &mut Connection<T>, atoms: &Atoms, window: x::Window) -> Result<bool, Error> {
+        let cookie = conn.send_request(&x::GetProperty {
+            delete: false,
+            window: window,
+            property: atoms.xembed_info,
+            r#type: atoms.xembed_info,
+            long_offset: 0,
+            long_length: 2,
+        });
+
+        let reply = conn.wait_for_reply(cookie)?;
+
+        match reply.value::<u32>() {
+            [_, flags] => Ok(flags & XEMBED_MAPPED != 0),
+            _ => Ok(true),
+        }
+    }
+
+    fn arrange<T>(&mut self, conn: &mut Connection<T>) -> Result<(), Error> {
+        let size = self.rect.h;
+        let mut x = 0;
+        let mut gone = vec![];
+
+        for icon in self.icons.iter() {
+            match alive(Tray::show(conn, icon, x, size))? {
+                Some(width) => x += width as i32,
+                None => gone.push(icon.window),
+            }
+        }
+
+        /* icons which have disappeared are dropped now, their
+         * DestroyNotify may still be on its way */
+        self.icons.retain(|icon| !gone.contains(&icon.window));
+
+        let width = self.width();
+
+        if width == 0 {
+            conn.send_and_check_request(&x::UnmapWindow {
+                window: self.window,
+            })?;
+
+            return Ok(());
+        }
+
+        /* keep the container above the bar it sits on */
+        conn.send_and_check_request(&x::ConfigureWindow {
+            window: self.window,
+            value_list: &[
+                x::ConfigWindow::X((self.rect.right() - width as i16) as i32),
+                x::ConfigWindow::Y(self.rect.y as i32),
+                x::ConfigWindow::Width(width as u32),
+                x::ConfigWindow::Height(size as u32),
+                x::ConfigWindow::StackMode(x::StackMode::Above),
+            ],
+        })?;
+
+        conn.send_and_check_request(&x::MapWindow {
+            window: self.window,
+        })?;
+
+        Ok(())
+    }
+
+    /// Place an icon at an offset in the tray, or hide it. Returns the
+    /// width taken up by the icon.
+    fn show<T>(conn: &mut Connection<T>, icon: &Icon, x: i32, size: u16) -> Result<u16, Error> {
+        if !icon.mapped || size == 0 {
+            conn.send_and_check_request(&x::UnmapWindow {
+                window: icon.window,
+            })?;
+
+            return Ok(0);
+        }
+
+        let width = icon.scale(size);
+
+        conn.send_and_check_request(&x::ConfigureWindow {
+            window: icon.window,
+            value_list: &[
+                x::ConfigWindow::X(x),
+                x::ConfigWindow::Y(0),
+                x::ConfigWindow::Width(width as u32),
+                x::ConfigWindow::Height(size as u32),
+            ],
+        })?;
+
+        conn.send_and_check_request(&x::MapWindow {
+            window: icon.window,
+        })?;
+
+        Ok(width)
+    }
+}
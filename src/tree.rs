@@ -162,7 +162,7 @@ impl<T> Tree<T> {
         let node = other.slab.remove(from);
         let index = self.insert(to, node.value);
 
-        for child in children.into_iter().rev() {
+        for child in children.into_iter() {
             self.graft(other, child, index);
         }
     }
@@ -180,23 +180,27 @@ impl<T> Tree<T> {
         root
     }
 
-    /// Extract a node from the tree and return its value, non-recursively
-    pub fn extract(&mut self, index: usize) -> T {
-        let root = self.slab.remove(index);
+    /// Unlink a node from its parent and siblings, keeping it and its
+    /// children in the tree as an orphan which can be adopted again
+    pub fn detach(&mut self, index: usize) {
+        let node = self.get_mut(index);
+        let parent = node.parent.take();
+        let left = node.left.take();
+        let right = node.right.take();
 
         /* check the left sibling. if there isn't one, we are the first child in
          * parent. otherwise, update the right sibling. */
-        match root.left {
+        match left {
             Some(i) => {
                 /* we have a sibling to the left. give it our right value */
-                self.get_mut(i).right = root.right;
+                self.get_mut(i).right = right;
             },
             None => {
                 /* we are the first child for our parent.
                  * update parent.first to have our right value */
-                match root.parent {
+                match parent {
                     Some(j) => {
-                        self.get_mut(j).first = root.right;
+                        self.get_mut(j).first = right;
                     },
                     None => {
                     }
@@ -206,25 +210,115 @@ impl<T> Tree<T> {
 
         /* check the right sibling. if there isn't one, we are the last child in
          * parent. otherwise, update the left sibling. */
-        match root.right {
+        match right {
             Some(i) => {
                 /* we have a sibling to the right. give it our left value */
-                self.get_mut(i).left = root.left;
+                self.get_mut(i).left = left;
             },
             None => {
                 /* we are the last child for our parent.
                  * update parent.last to have our left value */
-                match root.parent {
+                match parent {
                     Some(j) => {
-                        self.get_mut(j).last = root.left;
+                        self.get_mut(j).last = left;
                     },
                     None => {
                     }
                 }
             },
         }
+    }
+
+    /// Adopt an orphan into the parent of a node, directly before it
+    pub fn adopt_before(&mut self, sibling: usize, child: usize) {
+        let node = self.get(sibling);
+        let parent = node.parent.expect("sibling has no parent");
+        let left = node.left;
+
+        match left {
+            Some(i) => {
+                self.get_mut(i).right = Some(child);
+            }
+            None => {
+                self.get_mut(parent).first = Some(child);
+            }
+        }
+
+        self.get_mut(sibling).left = Some(child);
+
+        let node = self.get_mut(child);
+        node.parent = Some(parent);
+        node.left = left;
+        node.right = Some(sibling);
+    }
+
+    /// Move a node to directly before a sibling, which may be in another
+    /// parent
+    pub fn move_before(&mut self, index: usize, sibling: usize) {
+        if index != sibling {
+            self.detach(index);
+            self.adopt_before(sibling, index);
+        }
+    }
+
+    /// Move a node to directly after a sibling, which may be in another
+    /// parent
+    pub fn move_after(&mut self, index: usize, sibling: usize) {
+        if index == sibling {
+            return;
+        }
+
+        self.detach(index);
+
+        let node = self.get(sibling);
+
+        match (node.right, node.parent) {
+            (Some(right), _) => self.adopt_before(right, index),
+            (None, Some(parent)) => self.adopt(parent, index),
+            (None, None) => panic!("sibling has no parent"),
+        }
+    }
+
+    /// Swap the places of two nodes, along with their children
+    pub fn swap(&mut self, a: usize, b: usize) {
+        if a == b {
+            return;
+        }
+
+        let node = self.get(a);
+        let (right, parent) = (node.right, node.parent);
+
+        if right == Some(b) {
+            self.move_before(b, a);
+        } else {
+            self.move_before(a, b);
+
+            /* put b where a used to be */
+            match (right, parent) {
+                (Some(right), _) => self.move_before(b, right),
+                (None, Some(parent)) => {
+                    self.detach(b);
+                    self.adopt(parent, b);
+                }
+                (None, None) => panic!("node has no parent"),
+            }
+        }
+    }
+
+    /// Move a node to the start of its parent's children
+    pub fn move_to_front(&mut self, index: usize) {
+        let first = self.get(index).parent
+            .and_then(|parent| self.get(parent).first);
+
+        if let Some(first) = first {
+            self.move_before(index, first);
+        }
+    }
 
-        root.value
+    /// Extract a node from the tree and return its value, non-recursively
+    pub fn extract(&mut self, index: usize) -> T {
+        self.detach(index);
+        self.slab.remove(index).value
     }
 
     /// Extract a node from the tree and return its value, recursively discarding children
@@ -242,13 +336,12 @@ impl<T> Tree<T> {
     pub fn remove(&mut self, index: usize) -> Tree<T> {
         let children: Vec<_> = self.children(index).collect();
 
-        let root = self.slab.remove(index);
         let mut tree = Tree::new();
-        tree.swap_root(root.value);
+        tree.swap_root(self.extract(index));
 
         let parent = tree.root.unwrap();
 
-        for child in children.into_iter().rev() {
+        for child in children.into_iter() {
             tree.graft(self, child, parent);
         }
 
@@ -310,7 +403,10 @@ impl<'a, T> Iterator for IterAt<'a, T> {
     fn next(&mut self) -> Option<Self::Item> {
         let index = self.stack.pop()?;
 
-        self.stack.extend(self.tree.children(index));
+        /* push children in reverse so the first child is visited next,
+         * giving a pre-order walk of the sub-tree */
+        let children: Vec<_> = self.tree.children(index).collect();
+        self.stack.extend(children.into_iter().rev());
 
         Some(index)
     }
@@ -322,35 +418,127 @@ mod tests {
 
     fn children<T: Copy>(tree: &Tree<T>, index: usize) -> Vec<T> {
         let i: Vec<_> = tree.children(index).collect();
-        i.into_iter().map(|i| tree.get(&i).unwrap().value).collect()
+        i.into_iter().map(|i| tree.get(i).value).collect()
     }
 
     fn iter<T: Copy>(tree: &Tree<T>, index: usize) -> Vec<T> {
-        let i: Vec<_> = tree.iter(index).collect();
-        i.into_iter().map(|i| tree.get(&i).unwrap().value).collect()
+        let i: Vec<_> = tree.iter_at(index).collect();
+        i.into_iter().map(|i| tree.get(i).value).collect()
     }
 
     #[test]
     fn test_tree() {
-        let mut tree = Tree::new(1);
+        let mut tree = Tree::new();
+        tree.swap_root(1);
+
+        let root = tree.root().unwrap();
+
+        let two = tree.insert(root, 2);
+        tree.insert(two, 3);
+        let four = tree.insert(two, 4);
+
+        tree.insert(four, 5);
+        tree.insert(four, 6);
+        tree.insert(four, 7);
+
+        tree.insert(root, 8);
+
+        assert_eq!(children(&tree, root), vec![2, 8]);
+        assert_eq!(children(&tree, two), vec![3, 4]);
+        assert_eq!(children(&tree, four), vec![5, 6, 7]);
+        assert_eq!(iter(&tree, root), vec![1, 2, 3, 4, 5, 6, 7, 8]);
+
+        let new = tree.remove(two);
+        assert_eq!(iter(&tree, root), vec![1, 8]);
+        assert_eq!(iter(&new, new.root().unwrap()), vec![2, 3, 4, 5, 6, 7]);
+    }
+
+    #[test]
+    fn test_detach() {
+        let mut tree = Tree::new();
+        tree.swap_root(1);
+
+        let root = tree.root().unwrap();
+
+        let two = tree.insert(root, 2);
+        let three = tree.insert(root, 3);
+        let four = tree.insert(root, 4);
+
+        tree.detach(three);
+        assert_eq!(children(&tree, root), vec![2, 4]);
+        assert!(tree.get(three).parent().is_none());
 
-        let two = tree.insert(&tree.root(), 2).unwrap();
-        tree.insert(&two, 3).unwrap();
-        let four = tree.insert(&two, 4).unwrap();
+        tree.adopt_before(two, three);
+        assert_eq!(children(&tree, root), vec![3, 2, 4]);
 
-        tree.insert(&four, 5).unwrap();
-        tree.insert(&four, 6).unwrap();
-        tree.insert(&four, 7).unwrap();
+        tree.detach(four);
+        tree.adopt_before(two, four);
+        assert_eq!(children(&tree, root), vec![3, 4, 2]);
+        assert_eq!(tree.get(root).last_child(), Some(two));
+
+        /* a detached node keeps its own children */
+        tree.insert(four, 5);
+        tree.detach(four);
+        tree.adopt(root, four);
+        assert_eq!(iter(&tree, root), vec![1, 3, 2, 4, 5]);
+    }
+
+    #[test]
+    fn test_reorder() {
+        let mut tree = Tree::new();
+        tree.swap_root(0);
+
+        let root = tree.root().unwrap();
+
+        let one = tree.insert(root, 1);
+        let two = tree.insert(root, 2);
+        let three = tree.insert(root, 3);
+        let four = tree.insert(root, 4);
+
+        tree.move_after(one, three);
+        assert_eq!(children(&tree, root), vec![2, 3, 1, 4]);
+
+        tree.move_after(two, four);
+        assert_eq!(children(&tree, root), vec![3, 1, 4, 2]);
+        assert_eq!(tree.get(root).last_child(), Some(two));
+
+        tree.move_before(two, three);
+        assert_eq!(children(&tree, root), vec![2, 3, 1, 4]);
+
+        /* neighbours in either order, and nodes further apart */
+        tree.swap(two, three);
+        assert_eq!(children(&tree, root), vec![3, 2, 1, 4]);
+
+        tree.swap(one, two);
+        assert_eq!(children(&tree, root), vec![3, 1, 2, 4]);
+
+        tree.swap(three, four);
+        assert_eq!(children(&tree, root), vec![4, 1, 2, 3]);
+        assert_eq!(tree.get(root).first_child(), Some(four));
+        assert_eq!(tree.get(root).last_child(), Some(three));
+
+        tree.move_to_front(two);
+        assert_eq!(children(&tree, root), vec![2, 4, 1, 3]);
+
+        tree.move_to_front(two);
+        assert_eq!(children(&tree, root), vec![2, 4, 1, 3]);
+    }
+
+    #[test]
+    fn test_swap_parents() {
+        let mut tree = Tree::new();
+        tree.swap_root(0);
 
-        tree.insert(&tree.root(), 8).unwrap();
+        let root = tree.root().unwrap();
 
-        assert_eq!(children(&tree, &tree.root()), vec![8, 2]);
-        assert_eq!(children(&tree, &two), vec![4, 3]);
-        assert_eq!(children(&tree, &four), vec![7, 6, 5]);
-        assert_eq!(iter(&tree, &tree.root), vec![1, 2, 3, 4, 5, 6, 7, 8]);
+        let one = tree.insert(root, 1);
+        let two = tree.insert(one, 2);
+        tree.insert(one, 3);
+        let four = tree.insert(root, 4);
 
-        let new = tree.remove(&two).unwrap();
-        assert_eq!(iter(&tree, &tree.root), vec![1, 8]);
-        assert_eq!(iter(&new, &new.root), vec![2, 3, 4, 5, 6, 7]);
+        tree.swap(two, four);
+        assert_eq!(children(&tree, root), vec![1, 2]);
+        assert_eq!(children(&tree, one), vec![4, 3]);
+        assert_eq!(tree.get(four).parent(), Some(one));
     }
 }
@@ -1,10 +1,10 @@
 use std::ops::{Index, IndexMut};
 
 use crate::slab::AsIndex;
-use crate::client::Client;
+use crate::client::{Borders, Client};
 use crate::error::Error;
-use crate::layout::{Cell, Layout};
-use crate::rect::Rect;
+use crate::layout::{BinarySplit, Cell, Decoration, Gaps, Layout};
+use crate::rect::{Direction, Rect};
 use crate::tag::TagSelection;
 use crate::tree;
 use crate::wm::Connection;
@@ -15,6 +15,20 @@ use xcb::x;
 /// with their respective layouts.
 pub struct WindowTree {
     tree: tree::Tree<Window>,
+    decorations: Vec<Frame>,
+}
+
+/// A window drawn with the titles of a layout's children
+struct Frame {
+    /* index of the layout the frame belongs to */
+    layout: usize,
+    window: x::Window,
+    rect: Rect,
+    decoration: Decoration,
+    /* tree indices of the children shown, in the order of the tabs */
+    children: Vec<usize>,
+    /* whether the layout still had a decoration in the last arrange */
+    seen: bool,
 }
 
 /// Window is the node type of the window tree. It can either be a Client,
@@ -179,6 +193,51 @@ impl MaskTree {
         self.tree.root()
     }
 
+    /// Count the clients in the MaskTree, which are all visible
+    fn clients(&self, win: &WindowTree) -> usize {
+        self.tree.iter()
+            .filter(|(_, node)| {
+                matches!(win.tree.get(node.value).value, Window::Client(_))
+            })
+            .count()
+    }
+
+}
+
+/* split a gap between the top/left and bottom/right sides of a client, so
+ * the two halves meeting between neighbours add up to the whole gap */
+fn halves(gap: u16) -> (u16, u16) {
+    (gap / 2, gap - gap / 2)
+}
+
+/// State shared by every level of a single arrange
+struct Context<'a> {
+    masktree: &'a MaskTree,
+    /* the area given to the root layout */
+    scope: &'a Rect,
+    borders: &'a Borders,
+    /* space left between neighbouring clients */
+    gap: u16,
+    /* the window with input focus */
+    active: x::Window,
+}
+
+impl<'a> Context<'a> {
+    /* the part of a cell left to a client once the gap is taken out */
+    fn inset(&self, rect: &Rect) -> Rect {
+        let (near, far) = halves(self.gap);
+        rect.shrink(near, far, far, near)
+    }
+}
+
+/// Get the border width to use for a client arranged into a rect, which is
+/// hidden by smart borders when the client fills the whole layout area
+fn border(client: &Client, rect: &Rect, scope: &Rect, borders: &Borders) -> u16 {
+    if borders.smart && rect == scope {
+        0
+    } else {
+        client.border()
+    }
 }
 
 impl WindowTree {
@@ -190,6 +249,7 @@ impl WindowTree {
 
         WindowTree {
             tree: tree,
+            decorations: vec![],
         }
     }
 
@@ -217,12 +277,29 @@ impl WindowTree {
         }
     }
 
+    /// Replace the layout of a container, keeping its children. The old
+    /// layout is returned.
+    pub fn set_layout(&mut self, id: LayoutId, layout: Box<dyn Layout>) -> Box<dyn Layout> {
+        match self.tree.get_mut(id.inner).value {
+            Window::Layout(ref mut old) => std::mem::replace(old, layout),
+            _ => panic!("WindowTree: invalid layout id"),
+        }
+    }
+
     pub fn clients<'a>(&'a self) -> Clients<'a> {
         Clients {
             iter: self.tree.iter(),
         }
     }
 
+    /// Iterate clients in tree order, depth first from the root
+    pub fn walk<'a>(&'a self) -> Walk<'a> {
+        Walk {
+            tree: &self.tree,
+            iter: self.tree.iter_at(self.root().inner),
+        }
+    }
+
     /// Search for a client in the tree based on its window
     pub fn find(&self, window: x::Window) -> Option<ClientId> {
         self.tree.iter().find_map(|(id, node)| match node.value {
@@ -237,9 +314,126 @@ impl WindowTree {
         })
     }
 
-    /// Remove and return a node from the tree
+    /// Split a client in two, putting it and a new client side by side in
+    /// a new container. The direction is where the new client goes.
+    pub fn split(&mut self, id: ClientId, client: Client, direction: Direction) -> ClientId {
+        let layout: Box<dyn Layout> = match direction {
+            Direction::Left | Direction::Right => Box::new(BinarySplit::horizontal()),
+            Direction::Up | Direction::Down => Box::new(BinarySplit::vertical()),
+        };
+
+        /* the container takes the place of the client */
+        let container = self.tree.orphan(Window::Layout(layout));
+        self.tree.adopt_before(id.inner, container);
+        self.tree.detach(id.inner);
+
+        let child = self.tree.orphan(Window::Client(client));
+
+        match direction {
+            Direction::Left | Direction::Up => {
+                self.tree.adopt(container, child);
+                self.tree.adopt(container, id.inner);
+            }
+            Direction::Right | Direction::Down => {
+                self.tree.adopt(container, id.inner);
+                self.tree.adopt(container, child);
+            }
+        }
+
+        ClientId::from(child)
+    }
+
+    /// Move a client to the end of another layout. The layout it leaves
+    /// collapses if it needs to.
+    pub fn move_client(&mut self, id: ClientId, layout: LayoutId) {
+        let parent = self.tree.get(id.inner).parent();
+
+        if parent == Some(layout.inner) {
+            return;
+        }
+
+        self.tree.detach(id.inner);
+        self.tree.adopt(layout.inner, id.inner);
+
+        if let Some(parent) = parent {
+            self.collapse(parent);
+        }
+    }
+
+    /// Move a client into the nearest sibling container after it, or
+    /// before it if not going forward. Returns the container it moved into.
+    pub fn move_into_sibling(&mut self, id: ClientId, forward: bool) -> Option<LayoutId> {
+        let mut sibling = self.tree.get(id.inner).next_sibling();
+
+        if !forward {
+            sibling = self.tree.get(id.inner).previous_sibling();
+        }
+
+        while let Some(index) = sibling {
+            let node = self.tree.get(index);
+
+            if let Window::Layout(_) = node.value {
+                let layout = LayoutId::from(index);
+                self.move_client(id, layout);
+
+                return Some(layout);
+            }
+
+            sibling = if forward {
+                node.next_sibling()
+            } else {
+                node.previous_sibling()
+            };
+        }
+
+        None
+    }
+
+    /// Remove and return a node from the tree. Containers which collapse
+    /// are removed once they are left with a single child.
     pub fn remove<I: AsIndex>(&mut self, id: I) -> Window {
-        self.tree.prune(id.as_index())
+        let parent = self.tree.get(id.as_index()).parent();
+        let window = self.tree.prune(id.as_index());
+
+        if let Some(parent) = parent {
+            self.collapse(parent);
+        }
+
+        window
+    }
+
+    /// Replace a collapsing container with its only child, or remove it if
+    /// it has none left
+    fn collapse(&mut self, index: usize) {
+        if Some(index) == self.tree.root() {
+            return;
+        }
+
+        let node = self.tree.get(index);
+
+        match node.value {
+            Window::Layout(ref layout) if layout.collapse() => {}
+            _ => return,
+        }
+
+        let first = node.first_child();
+        let parent = node.parent();
+
+        if first.is_some() && first != node.last_child() {
+            return;
+        }
+
+        if let Some(child) = first {
+            self.tree.detach(child);
+            self.tree.adopt_before(index, child);
+        }
+
+        self.tree.extract(index);
+
+        /* removing an empty container may leave its parent to collapse */
+        if let (None, Some(parent)) = (first, parent) {
+            self.collapse(parent);
+        }
     }
 
     /// Show or hide an entire layout
@@ -276,12 +470,77 @@ impl WindowTree {
         Ok(())
     }
 
-    /// Arrange the windows in this tree, given a tag mask and a containing rectangle
+    /// Arrange the windows in this tree, given a tag mask and a containing
+    /// rectangle. The focused client is the one given, or the client with
+    /// input focus if there is none.
     pub fn arrange<'a, 'b, T>(
         &mut self,
         conn: &mut Connection<T>,
         mask: &TagSelection<'a, 'b>,
         rect: &Rect,
+        borders: &Borders,
+        gaps: &Gaps,
+        focus: Option<ClientId>,
+    ) -> Result<Option<ClientId>, Error> {
+        for frame in self.decorations.iter_mut() {
+            frame.seen = false;
+        }
+
+        let focus = self.arrange_root(conn, mask, rect, borders, gaps, focus)?;
+
+        /* destroy frames of layouts which are gone, hidden or no longer
+         * decorated */
+        for frame in self.decorations.iter().filter(|frame| !frame.seen) {
+            conn.send_and_check_request(&x::DestroyWindow {
+                window: frame.window,
+            })?;
+        }
+
+        self.decorations.retain(|frame| frame.seen);
+
+        Ok(focus)
+    }
+
+    /// Get the windows drawn for layout decorations, along with their
+    /// position and what to draw
+    pub fn decorations(&self) -> impl Iterator<Item = (x::Window, &Rect, &Decoration)> {
+        self.decorations.iter()
+            .map(|frame| (frame.window, &frame.rect, &frame.decoration))
+    }
+
+    /// Update the title of a client in the decorations showing it, without
+    /// arranging. Returns false if no decoration shows the client.
+    pub fn retitle(&mut self, id: ClientId) -> bool {
+        let name = String::from(self[id].name());
+        let mut changed = false;
+
+        for frame in self.decorations.iter_mut() {
+            let tab = frame.children.iter()
+                .position(|child| *child == id.inner)
+                .and_then(|i| frame.decoration.tabs.get_mut(i));
+
+            if let Some(tab) = tab {
+                tab.title = name.clone();
+                changed = true;
+            }
+        }
+
+        changed
+    }
+
+    /// Check whether a window was created for a layout decoration
+    pub fn decorated(&self, window: x::Window) -> bool {
+        self.decorations.iter().any(|frame| frame.window == window)
+    }
+
+    fn arrange_root<'a, 'b, T>(
+        &mut self,
+        conn: &mut Connection<T>,
+        mask: &TagSelection<'a, 'b>,
+        rect: &Rect,
+        borders: &Borders,
+        gaps: &Gaps,
+        focus: Option<ClientId>,
     ) -> Result<Option<ClientId>, Error> {
         if let Some(root) = self.tree.root() {
             /* request input focus, needed for Layout::arrange cells */
@@ -294,7 +553,33 @@ impl WindowTree {
             match masktree.root() {
                 Some(root) => {
                     /* there is at least one window present -- arrange it */
-                    self.arrange_recursive(conn, &masktree, root, rect, reply.focus())
+                    let gaps = if gaps.smart && masktree.clients(self) <= 1 {
+                        Gaps::default()
+                    } else {
+                        *gaps
+                    };
+
+                    /* every client is shrunk by half the inner gap on each
+                     * side, so the layout is given an area that much larger
+                     * to keep the outer gap exact */
+                    let (near, far) = halves(gaps.inner);
+                    let area = rect.inset(gaps.outer)
+                        .grow(near, far, far, near);
+
+                    let active = match focus.map(|id| &self.tree.get(id.inner).value) {
+                        Some(Window::Client(client)) => client.window(),
+                        _ => reply.focus(),
+                    };
+
+                    let context = Context {
+                        masktree: &masktree,
+                        scope: &area,
+                        borders: borders,
+                        gap: gaps.inner,
+                        active: active,
+                    };
+
+                    self.arrange_recursive(conn, &context, root, &area)
                 }
                 None => {
                     Ok(None)
@@ -308,15 +593,20 @@ impl WindowTree {
     fn arrange_recursive<T>(
         &mut self,
         conn: &mut Connection<T>,
-        masktree: &MaskTree,
+        context: &Context<'_>,
         index: usize,
         rect: &Rect,
-        active: x::Window,
     ) -> Result<Option<ClientId>, Error> {
+        let masktree = context.masktree;
+        let scope = context.scope;
+        let borders = context.borders;
+        let active = context.active;
 
         let mut focus = None;
 
         let mut cells = vec![];
+        let mut titles = vec![];
+        let mut children = vec![];
         let parent = masktree.get(index);
         let mut child = parent.first_child();
 
@@ -326,15 +616,29 @@ impl WindowTree {
             let window = self.tree.get(node.value);
 
             child = node.next_sibling();
+            children.push(node.value);
 
-            if let Window::Client(ref c) = window.value {
-                if c.window() == active {
-                    cells.push(Cell::Focus(*c.rect()));
-                } else {
-                    cells.push(Cell::Show(*c.rect()));
+            match window.value {
+                Window::Client(ref c) => {
+                    if c.window() == active {
+                        cells.push(Cell::Focus(*c.rect()));
+                    } else {
+                        cells.push(Cell::Show(*c.rect()));
+                    }
+
+                    titles.push(String::from(c.name()));
+                }
+                Window::Layout(ref l) => {
+                    /* a nested layout holding the focused client is focused
+                     * itself, so layouts showing one child can follow it */
+                    if self.contains(masktree, id, active) {
+                        cells.push(Cell::Focus(*rect));
+                    } else {
+                        cells.push(Cell::Hide);
+                    }
+
+                    titles.push(String::from(l.symbol()));
                 }
-            } else {
-                cells.push(Cell::Hide);
             }
         }
 
@@ -343,11 +647,18 @@ impl WindowTree {
         let node = masktree.get(index);
         let window = self.tree.get_mut(node.value);
 
-        match window.value {
+        let decoration = match window.value {
             Window::Layout(ref mut layout) => {
+                layout.titles(titles);
+                layout.children(&children);
                 layout.arrange(rect, &mut cells);
+                layout.decoration().cloned()
             }
-            _ => {}
+            _ => None,
+        };
+
+        if let Some(decoration) = decoration {
+            self.decorate(conn, context, node.value, decoration, children)?;
         }
 
         /* now the cells array is ready to be applied to the windows */
@@ -366,14 +677,24 @@ impl WindowTree {
                         client.show(conn, false)?;
                     }
                     Cell::Show(r) => {
+                        let border = border(client, r, scope, borders);
+                        let pixel = borders.pixel(client, false);
+
                         client.show(conn, true)?;
-                        client.resize(conn, r)?;
+                        client.resize(conn, &context.inset(r), border)?;
+                        client.paint(conn, pixel)?;
                     }
                     Cell::Focus(r) => {
-                        focus = Some(ClientId::from(i));
-                        client.focus(conn)?;
+                        let border = border(client, r, scope, borders);
+                        let pixel = borders.pixel(client, true);
+
+                        focus = Some(ClientId::from(node.value));
+                        client.set_urgent(false);
+                        /* a window can only be focused once it is mapped */
                         client.show(conn, true)?;
-                        client.resize(conn, r)?;
+                        client.resize(conn, &context.inset(r), border)?;
+                        client.paint(conn, pixel)?;
+                        client.focus(conn)?;
                     }
                 },
                 Window::Layout(_) => {
@@ -382,11 +703,10 @@ impl WindowTree {
                         Cell::Hide => {
                             self.show(conn, LayoutId::from(node.value), false)?;
                         }
-                        Cell::Show(r) => {
-                            focus = self.arrange_recursive(conn, masktree, id, r, active)?;
-                        }
-                        Cell::Focus(r) => {
-                            focus = self.arrange_recursive(conn, masktree, id, r, active)?;
+                        Cell::Show(r) | Cell::Focus(r) => {
+                            /* keep focus found by an earlier sibling */
+                            let nested = self.arrange_recursive(conn, context, id, r)?;
+                            focus = focus.or(nested);
                         }
                     }
                 }
@@ -398,59 +718,271 @@ impl WindowTree {
         Ok(focus)
     }
 
-    pub fn graft(&mut self, mut other: WindowTree) {
+    /// Check whether a node of the mask tree holds the focused client
+    fn contains(&self, masktree: &MaskTree, index: usize, active: x::Window) -> bool {
+        let node = masktree.get(index);
+
+        match self.tree.get(node.value).value {
+            Window::Client(ref c) => c.window() == active,
+            Window::Layout(_) => {
+                let mut child = node.first_child();
+
+                while let Some(id) = child {
+                    if self.contains(masktree, id, active) {
+                        return true;
+                    }
+
+                    child = masktree.get(id).next_sibling();
+                }
+
+                false
+            }
+        }
+    }
+
+    /// Create or move the frame drawn for a layout's decoration
+    fn decorate<T>(
+        &mut self,
+        conn: &mut Connection<T>,
+        context: &Context<'_>,
+        layout: usize,
+        decoration: Decoration,
+        children: Vec<usize>,
+    ) -> Result<(), Error> {
+        /* keep the strip in line with the gaps around clients */
+        let (near, far) = halves(context.gap);
+        let rect = decoration.rect.shrink(near, far, 0, near);
+
+        if rect.w == 0 || rect.h == 0 {
+            return Ok(());
+        }
+
+        let position = self.decorations.iter()
+            .position(|frame| frame.layout == layout && !frame.seen);
+
+        let window = match position {
+            Some(i) => self.decorations[i].window,
+            None => {
+                let window: x::Window = conn.generate_id();
+                let root = conn.root();
+
+                conn.send_and_check_request(&x::CreateWindow {
+                    depth: x::COPY_FROM_PARENT as u8,
+                    wid: window,
+                    parent: root,
+                    x: rect.x,
+                    y: rect.y,
+                    width: rect.w,
+                    height: rect.h,
+                    border_width: 0,
+                    class: x::WindowClass::InputOutput,
+                    visual: x::COPY_FROM_PARENT,
+                    value_list: &[
+                        x::Cw::OverrideRedirect(true),
+                        x::Cw::EventMask(x::EventMask::EXPOSURE),
+                    ],
+                })?;
+
+                window
+            }
+        };
+
+        conn.send_and_check_request(&x::ConfigureWindow {
+            window: window,
+            value_list: &[
+                x::ConfigWindow::X(rect.x as i32),
+                x::ConfigWindow::Y(rect.y as i32),
+                x::ConfigWindow::Width(rect.w as u32),
+                x::ConfigWindow::Height(rect.h as u32),
+            ],
+        })?;
+
+        conn.send_and_check_request(&x::MapWindow {
+            window: window,
+        })?;
+
+        let frame = Frame {
+            layout: layout,
+            window: window,
+            rect: rect,
+            decoration: decoration,
+            children: children,
+            seen: true,
+        };
+
+        match position {
+            Some(i) => self.decorations[i] = frame,
+            None => self.decorations.push(frame),
+        }
+
+        Ok(())
+    }
+
+    /// Move every window of another tree under our root, returning the
+    /// new ids of its clients
+    pub fn graft(&mut self, mut other: WindowTree) -> Vec<ClientId> {
         let root = other.root();
         let children: Vec<_> = other.tree.children(root.inner).collect();
+        let windows: Vec<_> = other.walk()
+            .map(|(_, client)| client.window())
+            .collect();
 
         let parent = self.root();
 
-        for child in children.into_iter().rev() {
+        for child in children.into_iter() {
             self.tree.graft(&mut other.tree, child, parent.inner);
         }
+
+        /* frames of the other tree are destroyed by the next arrange */
+        self.decorations.append(&mut other.decorations);
+
+        windows.into_iter()
+            .filter_map(|window| self.find(window))
+            .collect()
     }
 }
 
 
 impl WindowTree {
-    pub fn parent<I: AsIndex>(&self, i: I) -> Option<LayoutId> {
-        self.tree.get(i.as_index()).parent()
-            .map(|x| LayoutId::from(x))
+    /// Swap a client with the sibling after it, skipping siblings hidden
+    /// by a tag selection. Returns false if it is the last shown child of
+    /// its layout.
+    pub fn swap_next<'a, 'b>(&mut self, id: ClientId, mask: &TagSelection<'a, 'b>) -> bool {
+        let mut sibling = self.tree.get(id.inner).next_sibling();
+
+        while let Some(next) = sibling {
+            if self.shown(next, mask) {
+                self.tree.swap(id.inner, next);
+                return true;
+            }
+
+            sibling = self.tree.get(next).next_sibling();
+        }
+
+        false
     }
 
-    pub fn next_client<I: AsIndex>(&self, i: I) -> Option<ClientId> {
-        let mut node = self.tree.get(i.as_index());
+    /// Swap a client with the sibling before it, skipping siblings hidden
+    /// by a tag selection. Returns false if it is the first shown child of
+    /// its layout.
+    pub fn swap_previous<'a, 'b>(&mut self, id: ClientId, mask: &TagSelection<'a, 'b>) -> bool {
+        let mut sibling = self.tree.get(id.inner).previous_sibling();
 
-        loop {
-            let id = node.next_sibling()?;
-            node = self.tree.get(id);
+        while let Some(previous) = sibling {
+            if self.shown(previous, mask) {
+                self.tree.swap(id.inner, previous);
+                return true;
+            }
 
-            match node.value {
-                Window::Client(_) => {
-                    break Some(ClientId::from(id));
-                }
-                _ => {
-                    continue;
-                }
+            sibling = self.tree.get(previous).previous_sibling();
+        }
+
+        false
+    }
+
+    /// Check whether a node shows anything in a tag selection, which for a
+    /// layout means any client inside it
+    fn shown<'a, 'b>(&self, index: usize, mask: &TagSelection<'a, 'b>) -> bool {
+        match self.tree.get(index).value {
+            Window::Client(ref client) => client.masked(mask),
+            Window::Layout(_) => self.tree.children(index).any(|child| self.shown(child, mask)),
+        }
+    }
+
+    /// Swap the places of two clients, which may be in different layouts
+    #[inline]
+    pub fn swap(&mut self, a: ClientId, b: ClientId) {
+        self.tree.swap(a.inner, b.inner);
+    }
+
+    /// Move a client to the master slot at the front of its layout. The
+    /// master, the first child shown in a tag selection, is swapped with
+    /// the next shown child instead.
+    pub fn zoom<'a, 'b>(&mut self, id: ClientId, mask: &TagSelection<'a, 'b>) -> bool {
+        let mut sibling = self.tree.get(id.inner).previous_sibling();
+
+        while let Some(previous) = sibling {
+            if self.shown(previous, mask) {
+                self.tree.move_to_front(id.inner);
+                return true;
             }
+
+            sibling = self.tree.get(previous).previous_sibling();
         }
+
+        self.swap_next(id, mask)
     }
 
-    pub fn previous_client<I: AsIndex>(&self, i: I) -> Option<ClientId> {
-        let mut node = self.tree.get(i.as_index());
+    /// Rotate the children of a layout shown in a tag selection by one
+    /// place, leaving hidden children where they are. Going forward moves
+    /// the last shown child to the front.
+    pub fn rotate<'a, 'b>(&mut self, id: LayoutId, forward: bool, mask: &TagSelection<'a, 'b>) -> bool {
+        let shown: Vec<_> = self.tree.children(id.inner)
+            .filter(|child| self.shown(*child, mask))
+            .collect();
 
-        loop {
-            let id = node.previous_sibling()?;
-            node = self.tree.get(id);
+        if shown.len() < 2 {
+            return false;
+        }
 
-            match node.value {
-                Window::Client(_) => {
-                    break Some(ClientId::from(id));
-                }
-                _ => {
-                    continue;
-                }
+        /* carry the end child across each of the others in turn, which
+         * moves every other shown child one place along */
+        if forward {
+            let last = shown[shown.len() - 1];
+
+            for child in shown[..shown.len() - 1].iter().rev() {
+                self.tree.swap(last, *child);
+            }
+        } else {
+            let first = shown[0];
+
+            for child in shown[1..].iter() {
+                self.tree.swap(first, *child);
             }
         }
+
+        true
+    }
+
+    pub fn parent<I: AsIndex>(&self, i: I) -> Option<LayoutId> {
+        self.tree.get(i.as_index()).parent()
+            .map(|x| LayoutId::from(x))
+    }
+
+    /// Get the client after one in a depth-first walk of the whole tree,
+    /// skipping clients which are not visible in a tag selection and
+    /// wrapping around at the end. Without a client to start from, the
+    /// first visible client is used.
+    pub fn next_visible<'a, 'b>(&self, id: Option<ClientId>, mask: &TagSelection<'a, 'b>) -> Option<ClientId> {
+        let visible = self.visible(mask);
+        let position = id.and_then(|id| visible.iter().position(|x| *x == id));
+
+        match position {
+            Some(i) => Some(visible[(i + 1) % visible.len()]),
+            None => visible.first().copied(),
+        }
+    }
+
+    /// Get the client before one in a depth-first walk of the whole tree,
+    /// skipping clients which are not visible in a tag selection and
+    /// wrapping around at the start. Without a client to start from, the
+    /// last visible client is used.
+    pub fn previous_visible<'a, 'b>(&self, id: Option<ClientId>, mask: &TagSelection<'a, 'b>) -> Option<ClientId> {
+        let visible = self.visible(mask);
+        let position = id.and_then(|id| visible.iter().position(|x| *x == id));
+
+        match position {
+            Some(i) => Some(visible[(i + visible.len() - 1) % visible.len()]),
+            None => visible.last().copied(),
+        }
+    }
+
+    /// Get the clients visible in a tag selection, in depth-first order
+    fn visible<'a, 'b>(&self, mask: &TagSelection<'a, 'b>) -> Vec<ClientId> {
+        self.walk()
+            .filter(|(_, client)| client.masked(mask))
+            .map(|(id, _)| id)
+            .collect()
     }
 
     pub fn next_layout<I: AsIndex>(&self, i: I) -> Option<LayoutId> {
@@ -512,6 +1044,28 @@ impl<'a> Iterator for Clients<'a> {
     }
 }
 
+pub struct Walk<'a> {
+    tree: &'a tree::Tree<Window>,
+    iter: tree::IterAt<'a, Window>,
+}
+
+impl<'a> Iterator for Walk<'a> {
+    type Item = (ClientId, &'a Client);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let index = self.iter.next()?;
+
+            match self.tree.get(index).value {
+                Window::Client(ref client) => {
+                    break Some((ClientId::from(index), client));
+                },
+                _ => {},
+            }
+        }
+    }
+}
+
 impl Index<ClientId> for WindowTree {
     type Output = Client;
 
@@ -552,3 +1106,257 @@ impl IndexMut<LayoutId> for WindowTree {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::{LeftMaster, Tab};
+    use crate::tag::{Tag, TagMask, TagSet, Tags};
+
+    fn client(n: u32) -> Client {
+        let window = unsafe { xcb::XidNew::new(n) };
+        Client::new(window, Rect::new(0, 0, 1, 1))
+    }
+
+    fn order(tree: &WindowTree) -> Vec<ClientId> {
+        tree.walk().map(|(id, _)| id).collect()
+    }
+
+    #[test]
+    fn test_split() {
+        let mut tree = WindowTree::new(LeftMaster::new());
+        let root = tree.root();
+
+        let a = tree.client(root, client(1));
+        let b = tree.client(root, client(2));
+
+        let c = tree.split(a, client(3), Direction::Left);
+        assert_eq!(order(&tree), vec![c, a, b]);
+
+        let container = tree.parent(a).unwrap();
+        assert_eq!(tree.parent(c), Some(container));
+        assert_eq!(tree.parent(container), Some(root));
+        assert_eq!(tree[container].symbol(), "[|]");
+
+        let d = tree.split(a, client(4), Direction::Down);
+        assert_eq!(order(&tree), vec![c, a, d, b]);
+
+        /* removing d and then c collapses both containers */
+        tree.remove(d);
+        assert_eq!(tree.parent(a), Some(container));
+
+        tree.remove(c);
+        assert_eq!(tree.parent(a), Some(root));
+        assert_eq!(order(&tree), vec![a, b]);
+
+        /* the root layout never collapses */
+        tree.remove(b);
+        assert_eq!(tree.parent(a), Some(root));
+    }
+
+    #[test]
+    fn test_graft() {
+        let mut tree = WindowTree::new(LeftMaster::new());
+        let root = tree.root();
+        let a = tree.client(root, client(1));
+
+        let mut other = WindowTree::new(LeftMaster::new());
+        let root = other.root();
+        other.client(root, client(2));
+        let nested = other.layout(root, LeftMaster::new());
+        other.client(nested, client(3));
+
+        let moved = tree.graft(other);
+        assert_eq!(moved.len(), 2);
+        assert_eq!(order(&tree), vec![a, moved[0], moved[1]]);
+        assert_eq!(tree[moved[0]].window(), client(2).window());
+        assert_eq!(tree[moved[1]].window(), client(3).window());
+    }
+
+    #[test]
+    fn test_retitle() {
+        let mut tree = WindowTree::new(LeftMaster::new());
+        let root = tree.root();
+
+        let a = tree.client(root, client(1));
+        let b = tree.client(root, client(2));
+        let c = tree.client(root, client(3));
+
+        /* a frame showing a and c, with b hidden */
+        let tab = |title: &str| Tab {
+            title: String::from(title),
+            active: false,
+        };
+
+        tree.decorations.push(Frame {
+            layout: root.inner,
+            window: client(4).window(),
+            rect: Rect::new(0, 0, 1, 1),
+            decoration: Decoration {
+                rect: Rect::new(0, 0, 1, 1),
+                vertical: false,
+                tabs: vec![tab("a"), tab("c")],
+            },
+            children: vec![a.inner, c.inner],
+            seen: true,
+        });
+
+        tree[c].set_name(String::from("renamed"));
+        assert!(tree.retitle(c));
+
+        tree[b].set_name(String::from("hidden"));
+        assert!(!tree.retitle(b));
+
+        let titles: Vec<_> = tree.decorations()
+            .flat_map(|(_, _, decoration)| decoration.tabs.iter())
+            .map(|tab| tab.title.as_str())
+            .collect();
+
+        assert_eq!(titles, vec!["a", "renamed"]);
+    }
+
+    #[test]
+    fn test_reorder() {
+        let mut tree = WindowTree::new(LeftMaster::new());
+        let root = tree.root();
+
+        let a = tree.client(root, client(1));
+        let b = tree.client(root, client(2));
+        let c = tree.client(root, client(3));
+
+        /* without any tagsets every client is shown */
+        let tags = Tags::new();
+        let selection = tags.select(&[]);
+
+        assert!(tree.swap_next(a, &selection));
+        assert_eq!(order(&tree), vec![b, a, c]);
+        assert!(!tree.swap_previous(b, &selection));
+
+        assert!(tree.zoom(c, &selection));
+        assert_eq!(order(&tree), vec![c, b, a]);
+
+        /* zooming the master swaps it with the next client */
+        assert!(tree.zoom(c, &selection));
+        assert_eq!(order(&tree), vec![b, c, a]);
+
+        assert!(tree.rotate(root, true, &selection));
+        assert_eq!(order(&tree), vec![a, b, c]);
+
+        assert!(tree.rotate(root, false, &selection));
+        assert_eq!(order(&tree), vec![b, c, a]);
+    }
+
+    #[test]
+    fn test_reorder_hidden() {
+        let mut tags = Tags::new();
+        let tid = tags.insert(TagSet::new(&["a", "b"]));
+        let ids = [tid];
+
+        let mut tree = WindowTree::new(LeftMaster::new());
+        let root = tree.root();
+
+        let a = tree.client(root, client(1));
+        let b = tree.client(root, client(2));
+        let c = tree.client(root, client(3));
+
+        /* b is only on the second tag */
+        let mut mask = TagMask::new();
+        mask.clear();
+        mask.set(Tag::On(1));
+        tree[b].insert_mask(tid, mask);
+
+        let selection = tags.select(&ids);
+
+        /* swaps go past the hidden client */
+        assert!(tree.swap_next(a, &selection));
+        assert_eq!(order(&tree), vec![c, b, a]);
+        assert!(!tree.swap_next(a, &selection));
+        assert!(tree.swap_previous(a, &selection));
+        assert_eq!(order(&tree), vec![a, b, c]);
+
+        /* a is the master of what is shown, even behind a hidden client */
+        tree.swap(a, b);
+        assert_eq!(order(&tree), vec![b, a, c]);
+        assert!(tree.zoom(a, &selection));
+        assert_eq!(order(&tree), vec![b, c, a]);
+
+        assert!(tree.zoom(a, &selection));
+        assert_eq!(order(&tree), vec![a, b, c]);
+
+        /* rotating leaves the hidden client in place */
+        let d = tree.client(root, client(4));
+        assert!(tree.rotate(root, true, &selection));
+        assert_eq!(order(&tree), vec![d, b, a, c]);
+        assert!(tree.rotate(root, false, &selection));
+        assert_eq!(order(&tree), vec![a, b, c, d]);
+
+        tree.remove(d);
+        tree.remove(c);
+        assert!(!tree.rotate(root, true, &selection));
+    }
+
+    #[test]
+    fn test_move_into_sibling() {
+        let mut tree = WindowTree::new(LeftMaster::new());
+        let root = tree.root();
+
+        let a = tree.client(root, client(1));
+        let b = tree.client(root, client(2));
+        let c = tree.split(b, client(3), Direction::Right);
+        let container = tree.parent(b).unwrap();
+
+        assert_eq!(tree.move_into_sibling(a, true), Some(container));
+        assert_eq!(order(&tree), vec![b, c, a]);
+        assert_eq!(tree.parent(a), Some(container));
+
+        assert_eq!(tree.move_into_sibling(a, true), None);
+
+        /* moving the other clients out collapses the container */
+        tree.move_client(b, root);
+        tree.move_client(c, root);
+        assert_eq!(tree.parent(a), Some(root));
+        assert_eq!(order(&tree), vec![a, b, c]);
+    }
+
+    #[test]
+    fn test_visible_cycle() {
+        let mut tags = Tags::new();
+        let tid = tags.insert(TagSet::new(&["a", "b"]));
+        let ids = [tid];
+
+        let mut tree = WindowTree::new(LeftMaster::new());
+        let root = tree.root();
+
+        let a = tree.client(root, client(1));
+        let b = tree.split(a, client(2), Direction::Right);
+        let c = tree.split(b, client(3), Direction::Down);
+        let d = tree.client(root, client(4));
+
+        /* b is only on the second tag */
+        let mut mask = TagMask::new();
+        mask.clear();
+        mask.set(Tag::On(1));
+        tree[b].insert_mask(tid, mask);
+
+        let selection = tags.select(&ids);
+
+        assert_eq!(tree.next_visible(Some(a), &selection), Some(c));
+        assert_eq!(tree.next_visible(Some(c), &selection), Some(d));
+        assert_eq!(tree.next_visible(Some(d), &selection), Some(a));
+        assert_eq!(tree.previous_visible(Some(a), &selection), Some(d));
+        assert_eq!(tree.previous_visible(Some(d), &selection), Some(c));
+
+        /* a hidden or missing client starts from either end */
+        assert_eq!(tree.next_visible(Some(b), &selection), Some(a));
+        assert_eq!(tree.previous_visible(None, &selection), Some(d));
+    }
+
+    #[test]
+    fn test_halves() {
+        assert_eq!(halves(0), (0, 0));
+        assert_eq!(halves(4), (2, 2));
+
+        /* an odd gap keeps its last pixel on the far side */
+        assert_eq!(halves(5), (2, 3));
+    }
+}
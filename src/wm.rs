@@ -1,16 +1,23 @@
 use std::collections::VecDeque;
+use std::ffi::OsStr;
+use std::os::unix::process::CommandExt;
+use std::process::Command;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::ops::{Index, IndexMut};
 
-use crate::client::Client;
+use crate::atom::Atoms;
+use crate::client::{Borders, Client};
 use crate::display::{Display, Monitor, MonitorId};
 use crate::window::{Window, ClientId};
 use crate::error::Error;
-use crate::keyboard::{Binding, Keys};
-use crate::rect::Rect;
-use crate::tag::Tags;
+use crate::history::History;
+use crate::keyboard::{Binding, Keys, Modifier};
+use crate::rect::{Direction, Point, Rect};
+use crate::tag::{TagSelection, Tags};
+use crate::layout::Gaps;
 use crate::painter::Painter;
+use crate::tray::Tray;
 
 use fork::Fork;
 use signal_hook::consts::signal::*;
@@ -22,11 +29,20 @@ pub enum Event<T> {
     Interrupt,
     MonitorConnect(MonitorId),
     MonitorResize(MonitorId),
-    MonitorDisconnect(MonitorId),
+    MonitorDisconnect(MonitorId, Vec<(MonitorId, ClientId)>),
     MonitorPrimary(MonitorId),
     ClientCreate(MonitorId, ClientId),
     ClientDestroy(MonitorId, Client),
     ClientEnter(MonitorId, ClientId),
+    ClientName(MonitorId, ClientId),
+    BarExpose(MonitorId),
+    DecorationExpose(MonitorId),
+    BarClick(MonitorId, Point, x::Button, Modifier),
+    StatusText(String),
+    TrayUpdate,
+    /// A keyboard grab on the root window has ended, whether we released
+    /// it or it was lost
+    KeyboardUngrab,
     UserEvent(T),
 }
 
@@ -37,11 +53,18 @@ impl<T> std::fmt::Debug for Event<T> {
             Event::Interrupt => write!(f, "Event::Interrupt"),
             Event::MonitorConnect(_) => write!(f, "Event::MonitorConnect"),
             Event::MonitorResize(_) => write!(f, "Event::MonitorResize"),
-            Event::MonitorDisconnect(_) => write!(f, "Event::MonitorDisconnect"),
+            Event::MonitorDisconnect(_, _) => write!(f, "Event::MonitorDisconnect"),
             Event::MonitorPrimary(_) => write!(f, "Event::MonitorPrimary"),
             Event::ClientCreate(_, _) => write!(f, "Event::ClientCreate"),
             Event::ClientDestroy(_, _) => write!(f, "Event::ClientDestroy"),
             Event::ClientEnter(_, _) => write!(f, "Event::ClientEnter"),
+            Event::ClientName(_, _) => write!(f, "Event::ClientName"),
+            Event::BarExpose(_) => write!(f, "Event::BarExpose"),
+            Event::DecorationExpose(_) => write!(f, "Event::DecorationExpose"),
+            Event::BarClick(_, _, _, _) => write!(f, "Event::BarClick"),
+            Event::StatusText(_) => write!(f, "Event::StatusText"),
+            Event::TrayUpdate => write!(f, "Event::TrayUpdate"),
+            Event::KeyboardUngrab => write!(f, "Event::KeyboardUngrab"),
             Event::UserEvent(_) => write!(f, "Event::UserEvent"),
         }
     }
@@ -145,6 +168,22 @@ impl<T> Connection<T> {
         self.raw.check_request(cookie)
     }
 
+    /// Queue a request without checking it. Any error is reported later
+    /// through the event loop, so this is only for requests which are
+    /// batched, such as drawing.
+    #[inline]
+    pub fn send_request_unchecked<R>(&self, req: &R)
+    where
+        R: xcb::RequestWithoutReply,
+    {
+        self.raw.send_request(req);
+    }
+
+    #[inline]
+    pub fn flush(&self) -> Result<(), Error> {
+        Ok(self.raw.flush()?)
+    }
+
 
     #[inline]
     pub fn push(&mut self, e: Event<T>) {
@@ -159,10 +198,14 @@ impl<T> Connection<T> {
 
 pub struct WindowManager<T: Copy> {
     signal: Arc<AtomicUsize>,
+    atoms: Atoms,
     painter: Painter,
     tags: Tags,
     display: Display,
     keys: Keys<T>,
+    tray: Option<Tray>,
+    /* clients by when they were last focused */
+    history: History<x::Window>,
 }
 
 impl<T: Copy> WindowManager<T> {
@@ -180,6 +223,7 @@ impl<T: Copy> WindowManager<T> {
             window: root,
             value_list: &[xcb::x::Cw::EventMask(
                 x::EventMask::STRUCTURE_NOTIFY
+                    | x::EventMask::FOCUS_CHANGE
                     | x::EventMask::PROPERTY_CHANGE
                     | x::EventMask::SUBSTRUCTURE_NOTIFY
                     | x::EventMask::SUBSTRUCTURE_REDIRECT,
@@ -187,9 +231,11 @@ impl<T: Copy> WindowManager<T> {
         })
         .map_err(|_| Error::AlreadyRunning)?;
 
+        let atoms = Atoms::intern_all(&conn.raw)?;
         let painter = Painter::new(conn, root)?;
         let tags = Tags::new();
         let keys = Keys::new(conn, root)?;
+        let tray = Tray::new(conn, &atoms)?;
 
         let display = Display::new(conn, root)?;
 
@@ -203,12 +249,20 @@ impl<T: Copy> WindowManager<T> {
 
         let wm = WindowManager {
             signal: Arc::new(AtomicUsize::new(0)),
+            atoms: atoms,
             display: display,
             painter: painter,
             keys: keys,
             tags: tags,
+            tray: tray,
+            history: History::new(),
         };
 
+        /* pick up any status text set before we started */
+        if let Some(status) = wm.name(conn, root)? {
+            conn.push(Event::StatusText(status));
+        }
+
         Ok(wm)
     }
 
@@ -276,10 +330,23 @@ impl<T: Copy> WindowManager<T> {
                     .get(focus, e.state(), e.detail() as Keycode, false);
                 Ok(value.map_or(Event::Empty, |x| Event::UserEvent(x)))
             }
+            xcb::Event::X(xcb::x::Event::FocusOut(ref e)) => {
+                if e.event() == conn.root && e.mode() == x::NotifyMode::Ungrab {
+                    Ok(Event::KeyboardUngrab)
+                } else {
+                    Ok(Event::Empty)
+                }
+            }
             xcb::Event::X(xcb::x::Event::ConfigureRequest(ref e)) => self.configure(conn, e),
             xcb::Event::X(xcb::x::Event::MapRequest(ref e)) => self.map(conn, e),
             xcb::Event::X(xcb::x::Event::EnterNotify(ref e)) => self.enter(conn, e),
             xcb::Event::X(xcb::x::Event::DestroyNotify(ref e)) => self.destroy(e),
+            xcb::Event::X(xcb::x::Event::Expose(ref e)) => self.expose(e),
+            xcb::Event::X(xcb::x::Event::ButtonPress(ref e)) => self.button(e),
+            xcb::Event::X(xcb::x::Event::PropertyNotify(ref e)) => self.property(conn, e),
+            xcb::Event::X(xcb::x::Event::ClientMessage(ref e)) => self.message(conn, e),
+            xcb::Event::X(xcb::x::Event::ReparentNotify(ref e)) => self.reparent(e),
+            xcb::Event::X(xcb::x::Event::SelectionClear(ref e)) => self.selection(conn, e),
             xcb::Event::RandR(xcb::randr::Event::ScreenChangeNotify(_)) => {
                 self.display.update(conn)?;
                 Ok(conn.pop().unwrap_or(Event::Empty))
@@ -303,6 +370,12 @@ impl<T: Copy> WindowManager<T> {
         self.keys.bind(conn, binding)
     }
 
+    /// Drop every binding local to a monitor
+    #[inline]
+    pub fn unbind(&mut self, conn: &mut Connection<T>, monitor: MonitorId) -> Result<(), Error> {
+        self.keys.unbind(conn, monitor)
+    }
+
     pub fn spawn(&self, cmd: &str) {
         /* xcb opens its descriptors with CLOEXEC */
         if let Some(args) = shlex::split(cmd) {
@@ -333,6 +406,97 @@ impl<T: Copy> WindowManager<T> {
         }
     }
 
+    /// Replace the running process with a new instance of the window
+    /// manager, passing it args. This only returns if the exec fails.
+    pub fn restart<S: AsRef<OsStr>>(&self, args: &[S]) -> Error {
+        /* prefer argv[0] over current_exe(), which points at the old
+         * binary once it has been rebuilt */
+        let program = match std::env::args_os().next() {
+            Some(program) => program,
+            None => match std::env::current_exe() {
+                Ok(path) => path.into_os_string(),
+                Err(e) => return Error::IoError(e),
+            },
+        };
+
+        /* xcb opens its descriptors with CLOEXEC, so our connection is
+         * closed and the new process can select SUBSTRUCTURE_REDIRECT */
+        Error::IoError(Command::new(program).args(args).exec())
+    }
+
+    /// Query the top level windows which already exist, returning each
+    /// unmanaged window that could be adopted along with whether it is mapped
+    pub fn scan(&mut self, conn: &mut Connection<T>) -> Result<Vec<(x::Window, bool)>, Error> {
+        let cookie = conn.send_request(&x::QueryTree {
+            window: conn.root,
+        });
+
+        let reply = conn.wait_for_reply(cookie)?;
+
+        let cookies: Vec<_> = reply.children()
+            .iter()
+            .map(|window| {
+                (*window, conn.send_request(&x::GetWindowAttributes {
+                    window: *window,
+                }))
+            })
+            .collect();
+
+        let mut windows = vec![];
+
+        for (window, cookie) in cookies {
+            /* the window may have been destroyed since QueryTree */
+            let attributes = match conn.wait_for_reply(cookie) {
+                Ok(attributes) => attributes,
+                Err(_) => continue,
+            };
+
+            if attributes.override_redirect() {
+                continue;
+            }
+
+            let managed = self.display.iter()
+                .any(|(_, mon)| mon.find(window).is_some());
+
+            if !managed {
+                let mapped = attributes.map_state() == x::MapState::Viewable;
+                windows.push((window, mapped));
+            }
+        }
+
+        Ok(windows)
+    }
+
+    /// Get the name of a window, preferring the UTF-8 _NET_WM_NAME over
+    /// the legacy WM_NAME
+    pub fn name(&self, conn: &mut Connection<T>, window: x::Window) -> Result<Option<String>, Error> {
+        let request = |property| x::GetProperty {
+            delete: false,
+            window: window,
+            property: property,
+            r#type: x::ATOM_ANY,
+            long_offset: 0,
+            long_length: u32::MAX / 4,
+        };
+
+        let net = conn.send_request(&request(self.atoms.net_wm_name));
+        let legacy = conn.send_request(&request(x::ATOM_WM_NAME));
+
+        /* collect both replies before failing, so neither is left behind */
+        let net = conn.wait_for_reply(net);
+        let legacy = conn.wait_for_reply(legacy);
+        let (net, legacy) = (net?, legacy?);
+
+        if net.r#type() == self.atoms.utf8_string {
+            Ok(Some(String::from_utf8_lossy(net.value::<u8>()).into_owned()))
+        } else if legacy.r#type() != x::ATOM_NONE {
+            /* STRING is latin-1, which maps directly onto unicode */
+            Ok(Some(legacy.value::<u8>().iter().map(|c| *c as char).collect()))
+        } else {
+            Ok(None)
+        }
+    }
+
     #[inline]
     pub fn get_monitor(&mut self) -> Option<MonitorId> {
         self.display.get_focus()
@@ -355,58 +519,188 @@ impl<T: Copy> WindowManager<T> {
           })
     }
 
-    pub fn next_client(&mut self) -> Option<(MonitorId, ClientId)> {
-        self.get_focus()
-            .and_then(|(mid, cid)| {
-                self[mid].next_client(cid)
-                    .map(|next| (mid, next))
-            })
+    /// Focus a client, recording it as the most recently used
+    pub fn set_focus(&mut self, conn: &mut Connection<T>, mid: MonitorId, cid: ClientId) -> Result<(), Error> {
+        self.display.set_focus(conn, mid, cid)?;
+        self.history.touch(self[mid][cid].window());
+
+        Ok(())
     }
 
-    pub fn previous_client(&mut self) -> Option<(MonitorId, ClientId)> {
-        self.get_focus()
-            .and_then(|(mid, cid)| {
-                self[mid].previous_client(cid)
-                    .map(|next| (mid, next))
+    /// Record the focused client as the most recently used. This picks up
+    /// focus which changed without set_focus, such as when arranging.
+    pub fn remember(&mut self) {
+        if let Some((mid, cid)) = self.get_focus() {
+            let window = self[mid][cid].window();
+            self.history.touch(window);
+        }
+    }
+
+    /// Get the nth most recently focused client, where 0 is the most
+    /// recent. This may be on any monitor, and hidden by its tags.
+    pub fn recent(&self, n: usize) -> Option<(MonitorId, ClientId)> {
+        self.history.get(n)
+            .and_then(|window| self.locate(window))
+    }
+
+    #[inline]
+    pub fn history(&self) -> &History<x::Window> {
+        &self.history
+    }
+
+    #[inline]
+    pub fn history_mut(&mut self) -> &mut History<x::Window> {
+        &mut self.history
+    }
+
+    /// Grab the whole keyboard while a modifier is held, returning false
+    /// if it is held elsewhere or the modifier is already up
+    #[inline]
+    pub fn grab_keyboard(&mut self, conn: &mut Connection<T>, modifier: Modifier) -> Result<bool, Error> {
+        self.keys.grab_keyboard(conn, modifier)
+    }
+
+    #[inline]
+    pub fn ungrab_keyboard(&mut self, conn: &mut Connection<T>) -> Result<(), Error> {
+        self.keys.ungrab_keyboard(conn)
+    }
+
+    /// Find the monitor and id of the client for a window
+    fn locate(&self, window: x::Window) -> Option<(MonitorId, ClientId)> {
+        self.display.iter()
+            .find_map(|(mid, mon)| {
+                mon.find(window).map(|cid| {
+                    (mid, cid)
+                })
             })
     }
 
+    /// Focus the nearest visible client in a direction, which may be on a
+    /// neighbouring monitor. Returns the client focused, if any.
+    pub fn focus_direction(&mut self, conn: &mut Connection<T>, direction: Direction) -> Result<Option<(MonitorId, ClientId)>, Error> {
+        let neighbour = self.neighbour(direction);
+
+        if let Some((mid, cid)) = neighbour {
+            self.set_focus(conn, mid, cid)?;
+        }
+
+        Ok(neighbour)
+    }
+
+    /// Swap the focused client with the nearest visible client in a
+    /// direction, which may be on a neighbouring monitor. Returns where the
+    /// focused client ends up.
+    pub fn swap_direction(&mut self, direction: Direction) -> Option<(MonitorId, ClientId)> {
+        let focus = self.get_focus()?;
+        let neighbour = self.neighbour(direction)?;
+
+        Some(self.display.swap_clients(focus, neighbour))
+    }
+
+    /// Find the visible client nearest the focused one in a direction,
+    /// which may be on a neighbouring monitor
+    #[inline]
+    fn neighbour(&self, direction: Direction) -> Option<(MonitorId, ClientId)> {
+        self.display.neighbour(direction)
+    }
+
+    /// Get the visible client after the focused one on the focused monitor,
+    /// going through nested layouts and wrapping around at the end
+    pub fn next_client<'a, 'b>(&mut self, mask: &TagSelection<'a, 'b>) -> Option<(MonitorId, ClientId)> {
+        let mid = self.get_monitor()?;
+        let monitor = &self[mid];
+
+        monitor.next_visible(monitor.focus, mask)
+            .map(|next| (mid, next))
+    }
+
+    /// Get the visible client before the focused one on the focused
+    /// monitor, going through nested layouts and wrapping around at the
+    /// start
+    pub fn previous_client<'a, 'b>(&mut self, mask: &TagSelection<'a, 'b>) -> Option<(MonitorId, ClientId)> {
+        let mid = self.get_monitor()?;
+        let monitor = &self[mid];
+
+        monitor.previous_visible(monitor.focus, mask)
+            .map(|next| (mid, next))
+    }
+
     pub fn get_painter_mut(&mut self) -> &mut Painter {
         &mut self.painter
     }
+
+    /// Set the border width and colours of all clients
+    #[inline]
+    pub fn set_borders(&mut self, borders: Borders) {
+        self.display.set_borders(borders);
+    }
+
+    /// Set the gaps of every monitor
+    #[inline]
+    pub fn set_gaps(&mut self, gaps: Gaps) {
+        self.display.set_gaps(gaps);
+    }
+
+    /// Get the system tray, if we own one
+    #[inline]
+    pub fn tray(&self) -> Option<&Tray> {
+        self.tray.as_ref()
+    }
+
+    #[inline]
+    pub fn tray_mut(&mut self) -> Option<&mut Tray> {
+        self.tray.as_mut()
+    }
 }
 
 impl<T: Copy> WindowManager<T> {
     fn manage(&mut self, conn: &mut Connection<T>, window: x::Window) -> Result<Event<T>, Error> {
+        /* TODO: support empty output */
+        let mid = self.display.get_focus().unwrap();
+        let id = self.adopt(conn, mid, window, false)?;
+
+        Ok(Event::ClientCreate(mid, id))
+    }
+
+    /// Manage a window as a client of the given monitor. mapped should be
+    /// true if the window is already visible on screen.
+    pub fn adopt(
+        &mut self,
+        conn: &mut Connection<T>,
+        mid: MonitorId,
+        window: x::Window,
+        mapped: bool,
+    ) -> Result<ClientId, Error> {
         let rect = Rect::new(0, 0, 0, 0);
-        let client = Client::new(window, rect);
-        let id = self.display.client(client);
+        let mut client = if mapped {
+            Client::mapped(window, rect)
+        } else {
+            Client::new(window, rect)
+        };
 
         conn.send_and_check_request(&x::ChangeWindowAttributes {
             window: window,
             value_list: &[xcb::x::Cw::EventMask(
-                x::EventMask::ENTER_WINDOW,
+                x::EventMask::ENTER_WINDOW
+                    | x::EventMask::PROPERTY_CHANGE,
             )],
         })?;
 
-        let mid = self.display.get_focus().unwrap();
+        /* select PROPERTY_CHANGE before reading, so no update is missed */
+        if let Some(name) = self.name(conn, window)? {
+            client.set_name(name);
+        }
 
-        /* TODO: support empty output */
-        Ok(Event::ClientCreate(mid, id))
+        let id = self[mid].client(client);
+
+        Ok(id)
     }
 
     fn enter(&mut self, conn: &mut Connection<T>, e: &x::EnterNotifyEvent) -> Result<Event<T>, Error> {
-        let window = e.event();
-
-        let focus = self.display.iter()
-            .find_map(|(mid, mon)| {
-                mon.find(window).map(|cid| {
-                    (mid, cid)
-                })
-            });
+        let focus = self.locate(e.event());
 
         if let Some((mid, cid)) = focus {
-            self.display.set_focus(conn, mid, cid)?;
+            self.set_focus(conn, mid, cid)?;
             Ok(Event::ClientEnter(mid, cid))
         } else {
             Ok(Event::Empty)
@@ -415,6 +709,13 @@ impl<T: Copy> WindowManager<T> {
 
     /// handle a ConfigureRequestEvent, which is a request to configure a window's properties
     fn configure(&mut self, conn: &mut Connection<T>, event: &x::ConfigureRequestEvent) -> Result<Event<T>, Error> {
+        if let Some(ref mut tray) = self.tray {
+            if event.parent() == tray.window() {
+                tray.configure(conn, event)?;
+                return Ok(Event::TrayUpdate);
+            }
+        }
+
         let mask = event.value_mask();
         let mut values = Vec::with_capacity(7);
 
@@ -426,18 +727,23 @@ impl<T: Copy> WindowManager<T> {
                     .and_then(move |id| Some(&mon[id]))
             });
 
-        let rect = if let Some(c) = client {
-            *c.rect()
+        /* managed clients keep the geometry we gave them, anything else
+         * gets what it asked for */
+        let (rect, border) = if let Some(c) = client {
+            c.geometry()
         } else {
-            Rect::new(event.x(), event.y(), event.width(), event.height())
+            let rect = Rect::new(event.x(), event.y(), event.width(), event.height());
+            (rect, event.border_width())
         };
 
         values.push(x::ConfigWindow::X(rect.x as i32));
         values.push(x::ConfigWindow::Y(rect.y as i32));
-        values.push(x::ConfigWindow::Width(rect.w as u32));
-        values.push(x::ConfigWindow::Height(rect.h as u32));
+        values.push(x::ConfigWindow::Width(rect.w.max(1) as u32));
+        values.push(x::ConfigWindow::Height(rect.h.max(1) as u32));
 
-        values.push(x::ConfigWindow::BorderWidth(2));
+        if client.is_some() || mask.contains(xcb::x::ConfigWindowMask::BORDER_WIDTH) {
+            values.push(x::ConfigWindow::BorderWidth(border as u32));
+        }
 
         if mask.contains(xcb::x::ConfigWindowMask::SIBLING) {
             values.push(x::ConfigWindow::Sibling(event.sibling()));
@@ -460,15 +766,138 @@ impl<T: Copy> WindowManager<T> {
         let client = self.display.iter()
             .find_map(|(_, mon)| mon.find(e.window()));
 
-        if client.is_none() {
+        /* tray icons are mapped by the tray, following _XEMBED_INFO */
+        let icon = self.tray.as_ref()
+            .is_some_and(|tray| tray.contains(e.window()));
+
+        if client.is_none() && !icon {
             self.manage(conn, e.window())
         } else {
             Ok(Event::Empty)
         }
     }
 
-    /// handle the DestroyNotify, which notifies us that a window has been destroyed
-    fn destroy(&mut self, e: &x::DestroyNotifyEvent) -> Result<Event<T>, Error> {
+    /// handle an Expose event, which tells us part of a window needs redrawing
+    fn expose(&mut self, e: &x::ExposeEvent) -> Result<Event<T>, Error> {
+        /* only redraw once the last of a series of exposures arrives */
+        if e.count() > 0 {
+            return Ok(Event::Empty);
+        }
+
+        let bar = self.display.iter()
+            .find(|(_, mon)| mon.bar() == Some(e.window()));
+
+        if let Some((mid, _)) = bar {
+            return Ok(Event::BarExpose(mid));
+        }
+
+        let decoration = self.display.iter()
+            .find(|(_, mon)| mon.decorated(e.window()));
+
+        match decoration {
+            Some((mid, _)) => Ok(Event::DecorationExpose(mid)),
+            None => Ok(Event::Empty),
+        }
+    }
+
+    /// handle a ButtonPress, which we only select on bar windows
+    fn button(&mut self, e: &x::ButtonPressEvent) -> Result<Event<T>, Error> {
+        let bar = self.display.iter()
+            .find(|(_, mon)| mon.bar() == Some(e.event()));
+
+        match bar {
+            Some((mid, _)) => {
+                let point = Point {
+                    x: e.event_x(),
+                    y: e.event_y(),
+                };
+
+                let modifiers = self.keys.modifiers(e.state());
+
+                Ok(Event::BarClick(mid, point, e.detail(), modifiers))
+            }
+            None => {
+                Ok(Event::Empty)
+            }
+        }
+    }
+
+    /// handle a ClientMessage, which for us is only a request to dock an
+    /// icon in the tray
+    fn message(&mut self, conn: &mut Connection<T>, e: &x::ClientMessageEvent) -> Result<Event<T>, Error> {
+        let tray = match self.tray {
+            Some(ref mut tray) => tray,
+            None => return Ok(Event::Empty),
+        };
+
+        match tray.request(&self.atoms, e) {
+            Some(window) => {
+                tray.dock(conn, &self.atoms, window)?;
+                Ok(Event::TrayUpdate)
+            }
+            None => {
+                Ok(Event::Empty)
+            }
+        }
+    }
+
+    /// handle a ReparentNotify, which removes an icon from the tray if it
+    /// is taken by another window
+    fn reparent(&mut self, e: &x::ReparentNotifyEvent) -> Result<Event<T>, Error> {
+        if let Some(ref mut tray) = self.tray {
+            if e.parent() != tray.window() && tray.remove(e.window()) {
+                return Ok(Event::TrayUpdate);
+            }
+        }
+
+        Ok(Event::Empty)
+    }
+
+    /// handle a SelectionClear, which means another tray has replaced ours
+    fn selection(&mut self, conn: &mut Connection<T>, e: &x::SelectionClearEvent) -> Result<Event<T>, Error> {
+        let lost = self.tray.as_ref()
+            .is_some_and(|tray| tray.selection() == e.selection());
+
+        if lost {
+            if let Some(tray) = self.tray.take() {
+                tray.destroy(conn)?;
+            }
+
+            Ok(Event::TrayUpdate)
+        } else {
+            Ok(Event::Empty)
+        }
+    }
+
+    /// handle a PropertyNotify, which tells us a property of a window we
+    /// are watching has changed
+    fn property(&mut self, conn: &mut Connection<T>, e: &x::PropertyNotifyEvent) -> Result<Event<T>, Error> {
+        let atom = e.atom();
+        let named = atom == x::ATOM_WM_NAME || atom == self.atoms.net_wm_name;
+
+        if atom == self.atoms.xembed_info {
+            if let Some(ref mut tray) = self.tray {
+                if tray.contains(e.window()) {
+                    tray.update(conn, &self.atoms, e.window())?;
+                    return Ok(Event::TrayUpdate);
+                }
+            }
+        }
+
+        if atom == x::ATOM_WM_HINTS {
+            return self.hints(conn, e.window());
+        }
+
+        if !named {
+            return Ok(Event::Empty);
+        }
+
+        if e.window() == conn.root {
+            /* the root window name is used as status text, like dwm */
+            let status = self.name(conn, e.window())?;
+            return Ok(Event::StatusText(status.unwrap_or_default()));
+        }
+
         let ids = self.display.iter()
             .find_map(|(mid, mon)| {
                 mon.find(e.window()).map(|cid| {
@@ -478,6 +907,93 @@ impl<T: Copy> WindowManager<T> {
 
         match ids {
             Some((mid, cid)) => {
+                /* the client may be destroyed before the name arrives */
+                let name = match self.name(conn, e.window()) {
+                    Ok(name) => name,
+                    Err(e) if e.is_bad_window() => return Ok(Event::Empty),
+                    Err(e) => return Err(e),
+                };
+
+                self[mid][cid].set_name(name.unwrap_or_default());
+
+                Ok(Event::ClientName(mid, cid))
+            }
+            None => {
+                Ok(Event::Empty)
+            }
+        }
+    }
+
+    /// Read the urgency hint of a client from WM_HINTS, and repaint its
+    /// border if it changed
+    fn hints(&mut self, conn: &mut Connection<T>, window: x::Window) -> Result<Event<T>, Error> {
+        /* the urgency hint is bit 8 of the WM_HINTS flags */
+        const URGENCY_HINT: u32 = 1 << 8;
+
+        let ids = self.display.iter()
+            .find_map(|(mid, mon)| {
+                mon.find(window).map(|cid| {
+                    (mid, cid)
+                })
+            });
+
+        let (mid, cid) = match ids {
+            Some(ids) => ids,
+            None => return Ok(Event::Empty),
+        };
+
+        let cookie = conn.send_request(&x::GetProperty {
+            delete: false,
+            window: window,
+            property: x::ATOM_WM_HINTS,
+            r#type: x::ATOM_WM_HINTS,
+            long_offset: 0,
+            long_length: 1,
+        });
+
+        /* the client may be destroyed before the reply arrives, or
+         * before its border is painted, which leaves nothing to do */
+        let reply = match conn.wait_for_reply(cookie) {
+            Ok(reply) => reply,
+            Err(e) if e.is_bad_window() => return Ok(Event::Empty),
+            Err(e) => return Err(e),
+        };
+
+        let urgent = reply.value::<u32>()
+            .first()
+            .is_some_and(|flags| flags & URGENCY_HINT != 0);
+
+        /* the focused client is never shown as urgent */
+        let mon = &mut self[mid];
+        let urgent = urgent && mon.focus != Some(cid);
+
+        if mon[cid].urgent() != urgent {
+            mon[cid].set_urgent(urgent);
+
+            if let Err(e) = mon.paint(conn, cid) {
+                if !e.is_bad_window() {
+                    return Err(e);
+                }
+            }
+        }
+
+        Ok(Event::Empty)
+    }
+
+    /// handle the DestroyNotify, which notifies us that a window has been destroyed
+    fn destroy(&mut self, e: &x::DestroyNotifyEvent) -> Result<Event<T>, Error> {
+        if let Some(ref mut tray) = self.tray {
+            if tray.remove(e.window()) {
+                return Ok(Event::TrayUpdate);
+            }
+        }
+
+        let ids = self.locate(e.window());
+
+        match ids {
+            Some((mid, cid)) => {
+                self.history.remove(e.window());
+
                 match self[mid].remove(cid) {
                     Window::Client(c) => Ok(Event::ClientDestroy(mid, c)),
                     _ => panic!("Invalid client ID"),